@@ -0,0 +1,188 @@
+//! Colors that can be written as hex strings, X11 color names, X resources or names from a shared theme.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::RwLock;
+
+use crate::common::x11::read_xresources;
+
+
+/// Prefix for colors referring to X resources (like `xrdb:color4`)
+pub const XRESOURCES_PREFIX: &str = "xrdb:";
+
+/// Named colors of the currently loaded theme
+static THEME_COLORS: RwLock<BTreeMap<String, u64>> = RwLock::new(BTreeMap::new());
+
+/// Currently loaded X resources
+static XRESOURCES: RwLock<BTreeMap<String, String>> = RwLock::new(BTreeMap::new());
+
+
+/// A color represented as pixel value (`0xAARRGGBB`)
+///
+/// Colors created from a name remember it, so they are serialized the same way they were written.
+/// Names are only looked up completely by [Color::resolve], as X11 color names require a connection to
+/// the X server.
+#[derive(Clone,PartialEq,Eq,Debug,Default)]
+pub struct Color {
+    pixel: u64,
+    name: Option<String>,
+}
+
+
+impl Color {
+    /// Create a color from a name of the X resources, the theme or the X11 color database
+    ///
+    /// Names that are not known yet (like X11 color names) are black until they are resolved.
+    pub fn from_name(name: &str) -> Color {
+        let pixel = lookup_name(name, &|_| None).unwrap_or(0);
+        Color { pixel, name: Some(name.to_owned()) }
+    }
+
+    /// Name the color was created from (if any)
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Pixel value of the color
+    pub fn pixel(&self) -> u64 {
+        self.pixel
+    }
+
+    /// Look up the color by its name again (e.g. after connecting or after the X resources changed)
+    ///
+    /// X11 color names are looked up with `color_by_name` (like [parse_color_name] on an open
+    /// display). If the name cannot be resolved, the previous value is kept.
+    ///
+    /// [parse_color_name]: crate::common::x11::parse_color_name
+    pub fn resolve(&mut self, color_by_name: &dyn Fn(&str) -> Option<u64>) -> Result<(), String> {
+        let name = match &self.name {
+            Some(name) => name,
+            None => return Ok(()),
+        };
+        match lookup_name(name, color_by_name) {
+            Some(pixel) => {
+                self.pixel = pixel;
+                Ok(())
+            },
+            None if name.starts_with(XRESOURCES_PREFIX) => Err(format!("unknown X resource color '{}'", name)),
+            None => Err(format!("unknown color '{}'", name)),
+        }
+    }
+}
+
+impl From<u64> for Color {
+    fn from(pixel: u64) -> Self {
+        Color { pixel, name: None }
+    }
+}
+
+impl std::str::FromStr for Color {
+    type Err = String;
+
+    /// Parse `#rrggbb`, `#rrggbbaa` or a color name
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(hex) = s.strip_prefix('#') {
+            let value = u64::from_str_radix(hex, 16)
+                .map_err(|_| format!("invalid hex color '{}'", s))?;
+            match hex.len() {
+                6 => Ok(Color::from(value)),
+                8 => Ok(Color::from(((value & 0xff) << 24) | (value >> 8))),
+                _ => Err(format!("invalid hex color '{}' (expected #rrggbb or #rrggbbaa)", s)),
+            }
+        } else if let Some(hex) = s.strip_prefix("0x") {
+            u64::from_str_radix(hex, 16).map(Color::from)
+                .map_err(|_| format!("invalid hex color '{}'", s))
+        } else if s.is_empty() {
+            Err("empty color name".to_owned())
+        } else {
+            Ok(Color::from_name(s))
+        }
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(name) = &self.name {
+            write!(f, "{}", name)
+        } else if self.pixel >> 24 != 0 {
+            write!(f, "#{:06x}{:02x}", self.pixel & 0xffffff, (self.pixel >> 24) & 0xff)
+        } else {
+            write!(f, "#{:06x}", self.pixel)
+        }
+    }
+}
+
+#[cfg(feature = "configuration")]
+impl serde::Serialize for Color {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+#[cfg(feature = "configuration")]
+impl<'de> serde::Deserialize<'de> for Color {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ColorVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for ColorVisitor {
+            type Value = Color;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a color (like \"#30d6ff\" or \"DarkSlateGray\") or a number")
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, value: u64) -> Result<Color, E> {
+                Ok(Color::from(value))
+            }
+
+            fn visit_i64<E: serde::de::Error>(self, value: i64) -> Result<Color, E> {
+                u64::try_from(value).map(Color::from)
+                    .map_err(|_| E::custom(format!("invalid color value {}", value)))
+            }
+
+            fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<Color, E> {
+                value.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_any(ColorVisitor)
+    }
+}
+
+
+/// Read the X resources again, so they can be referred to by colors
+pub fn load_xresources() {
+    if let Ok(mut xresources) = XRESOURCES.write() {
+        *xresources = read_xresources();
+    }
+}
+
+/// Replace the named colors of the theme
+pub fn set_theme_colors(colors: BTreeMap<String, u64>) {
+    if let Ok(mut theme_colors) = THEME_COLORS.write() {
+        *theme_colors = colors;
+    }
+}
+
+fn lookup_name(name: &str, color_by_name: &dyn Fn(&str) -> Option<u64>) -> Option<u64> {
+    if let Some(resource) = name.strip_prefix(XRESOURCES_PREFIX) {
+        xresources_color(resource, color_by_name)
+    } else {
+        theme_color(name).or_else(|| color_by_name(name))
+    }
+}
+
+fn theme_color(name: &str) -> Option<u64> {
+    THEME_COLORS.read().ok()?.get(name).copied()
+}
+
+fn xresources_color(resource: &str, color_by_name: &dyn Fn(&str) -> Option<u64>) -> Option<u64> {
+    let value = XRESOURCES.read().ok()?.get(resource).cloned()?;
+    // resources must not refer to other resources
+    if value.starts_with(XRESOURCES_PREFIX) {
+        return None;
+    }
+    let mut color = value.parse::<Color>().ok()?;
+    color.resolve(color_by_name).ok()?;
+    Some(color.pixel)
+}
@@ -38,6 +38,13 @@ impl MarsError {
         }
     }
 
+    pub fn ipc_connect(path: impl Display) -> MarsError {
+        MarsError {
+            kind: MarsErrorKind::ConnectionFailed,
+            info: Some(format!("unable to connect to socket {}", path)),
+        }
+    }
+
     pub fn invalid_input(msg: impl ToString) -> MarsError {
         MarsError {
             kind: MarsErrorKind::InvalidInput,
@@ -52,6 +59,13 @@ impl MarsError {
         }
     }
 
+    pub fn request_rejected(msg: impl ToString) -> MarsError {
+        MarsError {
+            kind: MarsErrorKind::FailedRequest,
+            info: Some(msg.to_string()),
+        }
+    }
+
     pub fn property_unavailable(property: impl ToString) -> MarsError {
         MarsError {
             kind: MarsErrorKind::PropertyUnavailable,
@@ -1,6 +1,7 @@
 use std::cmp;
 
 
+pub mod color;
 pub mod error;
 pub mod x11;
 
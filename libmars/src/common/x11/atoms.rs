@@ -29,16 +29,28 @@ enum_with_values! {
         NetDesktopGeometry,
         NetDesktopNames,
         NetDesktopViewport,
+        NetFrameExtents,
+        NetMoveresizeWindow,
         NetNumberOfDesktops,
+        NetRequestFrameExtents,
         NetSupported,
         NetSupportingWMCheck,
         NetSystemTray,
         NetSystemTrayOpcode,
         NetSystemTrayOrientation,
+        NetWMActionChangeDesktop,
+        NetWMActionClose,
+        NetWMActionFullscreen,
+        NetWMActionMove,
+        NetWMActionResize,
+        NetWMAllowedActions,
         NetWMDesktop,
+        NetWMMoveresize,
         NetWMName,
         NetWMState,
+        NetWMStateDemandsAttention,
         NetWMStateFullscreen,
+        NetWMWindowOpacity,
         NetWMWindowType,
         NetWMWindowTypeDesktop,
         NetWMWindowTypeDialog,
@@ -58,7 +70,10 @@ enum_with_values! {
 
         // TODO add custom hints for pinned, tiled
         // MARS (custom)
+        MarsAction,
+        MarsBindingMode,
         MarsCenter,
+        MarsIpc,
         MarsStatus,
         MarsWMStateTiled
     }
@@ -85,16 +100,28 @@ impl Display for X11Atom {
             X11Atom::NetDesktopGeometry => "_NET_DESKTOP_GEOMETRY",
             X11Atom::NetDesktopNames => "_NET_DESKTOP_NAMES",
             X11Atom::NetDesktopViewport => "_NET_DESKTOP_VIEWPORT",
+            X11Atom::NetFrameExtents => "_NET_FRAME_EXTENTS",
+            X11Atom::NetMoveresizeWindow => "_NET_MOVERESIZE_WINDOW",
             X11Atom::NetNumberOfDesktops => "_NET_NUMBER_OF_DESKTOPS",
+            X11Atom::NetRequestFrameExtents => "_NET_REQUEST_FRAME_EXTENTS",
             X11Atom::NetSupported => "_NET_SUPPORTED",
             X11Atom::NetSupportingWMCheck => "_NET_SUPPORTING_WM_CHECK",
             X11Atom::NetSystemTray => "_NET_SYSTEM_TRAY_S0",
             X11Atom::NetSystemTrayOpcode => "_NET_SYSTEM_TRAY_OPCODE",
             X11Atom::NetSystemTrayOrientation => "_NET_SYSTEM_TRAY_ORIENTATION",
+            X11Atom::NetWMActionChangeDesktop => "_NET_WM_ACTION_CHANGE_DESKTOP",
+            X11Atom::NetWMActionClose => "_NET_WM_ACTION_CLOSE",
+            X11Atom::NetWMActionFullscreen => "_NET_WM_ACTION_FULLSCREEN",
+            X11Atom::NetWMActionMove => "_NET_WM_ACTION_MOVE",
+            X11Atom::NetWMActionResize => "_NET_WM_ACTION_RESIZE",
+            X11Atom::NetWMAllowedActions => "_NET_WM_ALLOWED_ACTIONS",
             X11Atom::NetWMDesktop => "_NET_WM_DESKTOP",
+            X11Atom::NetWMMoveresize => "_NET_WM_MOVERESIZE",
             X11Atom::NetWMName => "_NET_WM_NAME",
             X11Atom::NetWMState => "_NET_WM_STATE",
+            X11Atom::NetWMStateDemandsAttention => "_NET_WM_STATE_DEMANDS_ATTENTION",
             X11Atom::NetWMStateFullscreen => "_NET_WM_STATE_FULLSCREEN",
+            X11Atom::NetWMWindowOpacity => "_NET_WM_WINDOW_OPACITY",
             X11Atom::NetWMWindowType => "_NET_WM_WINDOW_TYPE",
             X11Atom::NetWMWindowTypeDock => "_NET_WM_WINDOW_TYPE_DOCK",
             X11Atom::NetWMWindowTypeDesktop => "_NET_WM_WINDOW_TYPE_DESKTOP",
@@ -110,7 +137,10 @@ impl Display for X11Atom {
             X11Atom::Xembed => "_XEMBED",
             X11Atom::XembedInfo => "_XEMBED_INFO",
 
+            X11Atom::MarsAction => "_MARS_ACTION",
+            X11Atom::MarsBindingMode => "_MARS_BINDING_MODE",
             X11Atom::MarsCenter => "_MARS_CENTER",
+            X11Atom::MarsIpc => "_MARS_IPC",
             X11Atom::MarsStatus => "_MARS_STATUS",
             X11Atom::MarsWMStateTiled => "_MARS_WM_STATE_TILED",
         };
@@ -1,10 +1,12 @@
 extern crate x11;
 
+use std::collections::BTreeMap;
 use std::collections::VecDeque;
 use std::ffi::*;
 use std::mem::MaybeUninit;
 use std::ptr;
 use std::slice;
+use x11::keysym;
 use x11::xinerama;
 use x11::xlib;
 use x11::xrandr;
@@ -27,6 +29,7 @@ pub const WINDOW_MIN_SIZE: u32 = 40;
 pub const CURSOR_NORMAL: u32 = 68;
 pub const CURSOR_RESIZE: u32 = 120;
 pub const CURSOR_MOVE: u32 = 52;
+pub const CURSOR_PICK: u32 = 34;
 // Window states
 pub const WITHDRAWN_STATE: i32 = 0;
 pub const NORMAL_STATE: i32 = 1;
@@ -80,8 +83,39 @@ impl From<*mut xlib::Screen> for MonitorConfig {
 }
 
 
+/// Checks whether a keysym belongs to a modifier key (like `IsModifierKey` from Xutil)
+pub fn is_modifier_key(keysym: u64) -> bool {
+    let keysym = keysym as u32;
+    (keysym::XK_Shift_L..=keysym::XK_Hyper_R).contains(&keysym)
+        || (keysym::XK_ISO_Lock..=keysym::XK_ISO_Level5_Lock).contains(&keysym)
+        || keysym == keysym::XK_Mode_switch
+        || keysym == keysym::XK_Num_Lock
+}
+
+/// Modifier mask a key code is mapped to (0 if it is not a modifier key)
+pub fn modifier_mask(display: *mut xlib::Display, keycode: u32) -> u32 {
+    unsafe {
+        let modmap = xlib::XGetModifierMapping(display);
+        if modmap.is_null() {
+            return 0;
+        }
+
+        let max_keypermod = (*modmap).max_keypermod as usize;
+        let keycodes = slice::from_raw_parts((*modmap).modifiermap, 8 * max_keypermod);
+        let mask = keycodes.iter().position(|kc| *kc as u32 == keycode)
+            .map(|i| 1 << (i / max_keypermod))
+            .unwrap_or(0);
+        xlib::XFreeModifiermap(modmap);
+        mask
+    }
+}
+
 /// Returs all permutiations of your modifiers with NumLock and Level3
 pub fn alternative_modifiers(modifiers: u32) -> Vec<u32> {
+    if modifiers & xlib::AnyModifier != 0 {
+        return vec![xlib::AnyModifier];
+    }
+
     vec![
         modifiers,
         modifiers | xlib::Mod2Mask,
@@ -183,6 +217,55 @@ pub fn open_display() -> Result<*mut xlib::Display> {
     }
 }
 
+/// Look up the RGB value of an X11 color name (like `DarkSlateGray`)
+pub fn parse_color_name(display: *mut xlib::Display, name: &str) -> Option<u64> {
+    let cstring = CString::new(name).ok()?;
+    let mut color: MaybeUninit<xlib::XColor> = MaybeUninit::uninit();
+    unsafe {
+        let colormap = xlib::XDefaultColormap(display, xlib::XDefaultScreen(display));
+        if xlib::XParseColor(display, colormap, cstring.as_ptr(), color.as_mut_ptr()) != 0 {
+            let color = color.assume_init();
+            Some(((color.red as u64 >> 8) << 16) | ((color.green as u64 >> 8) << 8) | (color.blue as u64 >> 8))
+        } else {
+            None
+        }
+    }
+}
+
+/// Read the X resources (`RESOURCE_MANAGER` property of the root window)
+///
+/// A new connection is used, so the returned resources are always up to date.
+/// Leading wildcards are stripped from the resource names (`*.color4` becomes `color4`).
+pub fn read_xresources() -> BTreeMap<String, String> {
+    let mut resources = BTreeMap::new();
+    let display = match open_display() {
+        Ok(display) => display,
+        Err(_) => return resources,
+    };
+
+    let resource_string = unsafe {
+        let ptr = xlib::XResourceManagerString(display);
+        if ptr.is_null() {
+            String::new()
+        } else {
+            CStr::from_ptr(ptr).to_string_lossy().to_string()
+        }
+    };
+    close_display(display);
+
+    for line in resource_string.lines() {
+        if line.starts_with('!') || line.starts_with('#') {
+            continue;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            let name = name.trim().trim_start_matches(['*', '.']);
+            resources.insert(name.to_owned(), value.trim().to_owned());
+        }
+    }
+
+    resources
+}
+
 /// Convert key names to [xlib::KeySym]
 ///
 /// * `name` - Name as defined in `X11/keysymdef.h` (without the 'XK_' prefix)
@@ -256,7 +339,7 @@ pub fn query_monitor_config(display: *mut xlib::Display, ignore_overlapping: boo
 
 /// Remove unrelated mask bits on button or key events
 pub fn sanitize_modifiers(modifiers: u32) -> u32 {
-    modifiers & (xlib::ShiftMask | xlib::ControlMask | xlib::Mod1Mask | xlib::Mod3Mask | xlib::Mod4Mask)
+    modifiers & (xlib::ShiftMask | xlib::ControlMask | xlib::Mod1Mask | xlib::Mod3Mask | xlib::Mod4Mask | xlib::Mod5Mask)
 }
 
 /// Send a ClientMessage to the default root window
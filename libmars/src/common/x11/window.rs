@@ -280,7 +280,7 @@ impl X11Window for xlib::Window {
             let result = xlib::XGetWindowProperty(display, *self, NetWMWindowType.to_xlib_atom(display),
                 0, 8, xlib::False, xlib::XA_ATOM,
                 &mut da, &mut di, &mut nitems, &mut dl, &mut win_types_ptr);
-            if result == xlib::Success.into() {
+            if result == i32::from(xlib::Success) {
                 let temp = slice::from_raw_parts(win_types_ptr as *mut xlib::Atom, nitems.try_into().unwrap());
                 types = temp.to_vec();
             }
@@ -348,7 +348,8 @@ impl X11Window for xlib::Window {
     }
 
     fn x11_wm_name(&self, display: *mut xlib::Display) -> Result<String> {
-        self.x11_read_property_string(display, WMName)
+        self.x11_read_property_string(display, NetWMName)
+            .or_else(|_| self.x11_read_property_string(display, WMName))
     }
 
     fn x11_wm_normal_hints(&self, display: *mut xlib::Display) -> Result<(xlib::XSizeHints, c_long)> {
@@ -0,0 +1,340 @@
+//! Communication with the window manager over a Unix domain socket.
+//!
+//! Each connection carries a single request, which is sent as one line of JSON and answered with
+//! one line of JSON.
+//! After a subscription request the connection stays open and receives one line per [WMEvent].
+//! Actions are passed on to the window manager as they are, so any action it knows can be used.
+
+use serde::{Serialize, Deserialize};
+use serde::de::DeserializeOwned;
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use x11::xlib;
+
+use crate::common::error::*;
+use crate::control::{ClientInfo, LayoutInfo, MonitorInfo, SettingMode, WMController, WMEvent, WorkspaceInfo};
+use crate::control::x11::X11Controller;
+
+
+/// Environment variable to override the location of the socket
+pub const SOCKET_ENV: &str = "MARSWM_SOCKET";
+
+
+/// Request sent to the window manager
+#[derive(Serialize,Deserialize,Clone,Debug,PartialEq)]
+pub struct IpcRequest<A> {
+    /// action to execute
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action: Option<A>,
+
+    /// information to query
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub query: Option<IpcQuery>,
+
+    /// window to execute the action on (defaults to the active window)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub window: Option<u64>,
+
+    /// keep the connection open to receive events
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub subscribe: bool,
+}
+
+/// Information that can be queried from the window manager
+#[derive(Serialize,Deserialize,Clone,Copy,Debug,PartialEq,Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum IpcQuery {
+    /// list of layout names
+    AvailableLayouts,
+    /// list of [ClientInfo]
+    Clients,
+    /// list of [LayoutInfo]
+    Layouts,
+    /// list of [MonitorInfo]
+    Monitors,
+    /// list of [WorkspaceInfo]
+    Workspaces,
+}
+
+/// Response of the window manager to a request
+#[derive(Serialize,Deserialize,Clone,Debug,PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum IpcResponse {
+    Ok(serde_json::Value),
+    Error(String),
+}
+
+/// Controls the window manager over its IPC socket
+///
+/// Requests that cannot be expressed as action or query are sent using the [X11Controller].
+pub struct IpcController {
+    path: PathBuf,
+    fallback: X11Controller,
+}
+
+
+impl<A> IpcRequest<A> {
+    pub fn action(action: A, window: Option<u64>) -> IpcRequest<A> {
+        IpcRequest { action: Some(action), query: None, window, subscribe: false }
+    }
+
+    pub fn query(query: IpcQuery) -> IpcRequest<A> {
+        IpcRequest { action: None, query: Some(query), window: None, subscribe: false }
+    }
+
+    pub fn subscribe() -> IpcRequest<A> {
+        IpcRequest { action: None, query: None, window: None, subscribe: true }
+    }
+}
+
+impl IpcController {
+    /// Connect to the socket of the running window manager
+    ///
+    /// Returns `None` if the window manager does not provide a socket.
+    pub fn new() -> Option<IpcController> {
+        let path = socket_path();
+        UnixStream::connect(&path).ok()?;
+        let fallback = X11Controller::new().ok()?;
+        Some(IpcController { path, fallback })
+    }
+
+    /// Execute an action (like `"close-client"` or `{"switch-workspace": 2}`)
+    pub fn execute(&self, action: serde_json::Value, window: Option<u64>) -> Result<()> {
+        self.send(&IpcRequest::action(action, window)).map(|_| ())
+    }
+
+    pub fn query<T: DeserializeOwned>(&self, query: IpcQuery) -> Result<T> {
+        let value = self.send(&IpcRequest::<serde_json::Value>::query(query))?;
+        serde_json::from_value(value).map_err(|_| MarsError::invalid_response(format!("{:?}", query)))
+    }
+
+    /// Send a request and wait for the response
+    pub fn send(&self, request: &IpcRequest<serde_json::Value>) -> Result<serde_json::Value> {
+        self.open(request).map(|(value, _)| value)
+    }
+
+    /// Send a request and return the response together with the connection for further reading
+    fn open(&self, request: &IpcRequest<serde_json::Value>) -> Result<(serde_json::Value, BufReader<UnixStream>)> {
+        let mut stream = UnixStream::connect(&self.path)
+            .map_err(|_| MarsError::ipc_connect(self.path.to_string_lossy()))?;
+        let mut line = serde_json::to_string(request).map_err(MarsError::invalid_input)?;
+        line.push('\n');
+        stream.write_all(line.as_bytes()).map_err(|_| MarsError::failed_request("writing to socket"))?;
+
+        let mut reader = BufReader::new(stream);
+        let mut response = String::new();
+        reader.read_line(&mut response)
+            .map_err(|_| MarsError::failed_request("reading from socket"))?;
+        match serde_json::from_str(&response) {
+            Ok(IpcResponse::Ok(value)) => Ok((value, reader)),
+            Ok(IpcResponse::Error(msg)) => Err(MarsError::request_rejected(msg)),
+            Err(_) => Err(MarsError::invalid_response(line.trim())),
+        }
+    }
+
+    fn client(&self, window: xlib::Window) -> Result<ClientInfo> {
+        self.query::<Vec<ClientInfo>>(IpcQuery::Clients)?.into_iter()
+            .find(|c| c.window == window)
+            .ok_or_else(|| MarsError::invalid_input(format!("no client with window id {}", window)))
+    }
+
+    /// Toggle a setting with an action, if the current state does not already match the mode
+    fn set_state(&self, window: xlib::Window, state: bool, mode: SettingMode, toggle_action: &str) -> Result<()> {
+        let toggle = match mode {
+            SettingMode::Set => !state,
+            SettingMode::Unset => state,
+            SettingMode::Toggle => true,
+        };
+        if toggle {
+            self.execute(serde_json::json!(toggle_action), Some(window))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl WMController<xlib::Window> for IpcController {
+    fn activate_window(&self, window: xlib::Window) -> Result<()> {
+        self.fallback.activate_window(window)
+    }
+
+    fn available_layouts(&self) -> Result<Vec<String>> {
+        self.query(IpcQuery::AvailableLayouts)
+    }
+
+    fn center_window(&self, window: xlib::Window) -> Result<()> {
+        self.execute(serde_json::json!("center-client"), Some(window))
+    }
+
+    fn clients(&self) -> Result<Vec<ClientInfo>> {
+        self.query(IpcQuery::Clients)
+    }
+
+    fn close_window(&self, window: xlib::Window) -> Result<()> {
+        self.execute(serde_json::json!("close-client"), Some(window))
+    }
+
+    fn count_workspaces(&self) -> Result<u32> {
+        Ok(self.query::<Vec<WorkspaceInfo>>(IpcQuery::Workspaces)?.len() as u32)
+    }
+
+    fn current_workspace(&self) -> Result<u32> {
+        self.query::<Vec<WorkspaceInfo>>(IpcQuery::Workspaces)?.iter()
+            .find(|ws| ws.focused)
+            .map(|ws| ws.index)
+            .ok_or(MarsError::invalid_response("current workspace"))
+    }
+
+    fn execute_action(&self, window: Option<xlib::Window>, action: &str) -> Result<()> {
+        let value: serde_yaml::Value = serde_yaml::from_str(action)
+            .map_err(|e| MarsError::invalid_input(format!("invalid action '{}': {}", action, e)))?;
+        self.execute(yaml_to_json(value), window)
+    }
+
+    fn fullscreen_window(&self, window: xlib::Window, mode: SettingMode) -> Result<()> {
+        let state = self.window_is_fullscreen(window)?;
+        self.set_state(window, state, mode, "toggle-fullscreen")
+    }
+
+    fn get_active_window(&self) -> Result<xlib::Window> {
+        self.query::<Vec<ClientInfo>>(IpcQuery::Clients)?.iter()
+            .find(|c| c.active)
+            .map(|c| c.window)
+            .ok_or(MarsError::property_unavailable("active window"))
+    }
+
+    fn get_workspace(&self, window: xlib::Window) -> Result<u32> {
+        match self.client(window)?.workspace {
+            Some(workspace) => Ok(workspace),
+            None => self.current_workspace(),
+        }
+    }
+
+    fn layout(&self, workspace: u32) -> Result<String> {
+        self.query::<Vec<LayoutInfo>>(IpcQuery::Layouts)?.into_iter()
+            .find(|l| l.workspace == workspace)
+            .map(|l| l.layout)
+            .ok_or_else(|| MarsError::invalid_input(format!("no workspace with index {}", workspace)))
+    }
+
+    fn monitors(&self) -> Result<Vec<MonitorInfo>> {
+        self.query(IpcQuery::Monitors)
+    }
+
+    fn pick_window(&self) -> Result<xlib::Window> {
+        self.fallback.pick_window()
+    }
+
+    fn pin_window(&self, window: xlib::Window, mode: SettingMode) -> Result<()> {
+        self.fallback.pin_window(window, mode)
+    }
+
+    fn send_window_to_workspace(&self, window: xlib::Window, workspace: u32) -> Result<()> {
+        // actions only address workspaces relative to the client's monitor
+        self.fallback.send_window_to_workspace(window, workspace)
+    }
+
+    fn set_status(&self, status: String) -> Result<()> {
+        self.fallback.set_status(status)
+    }
+
+    fn subscribe(&self, handler: &mut dyn FnMut(WMEvent)) -> Result<()> {
+        let (_, reader) = self.open(&IpcRequest::subscribe())?;
+        for line in reader.lines() {
+            let line = line.map_err(|_| MarsError::failed_request("reading from socket"))?;
+            // ignore events unknown to this version
+            if let Ok(event) = serde_json::from_str(&line) {
+                handler(event);
+            }
+        }
+        Ok(())
+    }
+
+    fn switch_workspace(&self, workspace: u32) -> Result<()> {
+        self.execute(serde_json::json!({ "switch-workspace": workspace }), None)
+    }
+
+    fn tile_window(&self, window: xlib::Window, mode: SettingMode) -> Result<()> {
+        let state = self.window_is_tiled(window)?;
+        self.set_state(window, state, mode, "toggle-floating")
+    }
+
+    fn window_is_fullscreen(&self, window: xlib::Window) -> Result<bool> {
+        Ok(self.client(window)?.fullscreen)
+    }
+
+    fn window_is_pinned(&self, window: xlib::Window) -> Result<bool> {
+        Ok(self.client(window)?.pinned)
+    }
+
+    fn window_is_tiled(&self, window: xlib::Window) -> Result<bool> {
+        Ok(self.client(window)?.tiled)
+    }
+
+    fn workspaces(&self) -> Result<Vec<String>> {
+        let workspaces = self.query::<Vec<WorkspaceInfo>>(IpcQuery::Workspaces)?;
+        Ok(workspaces.into_iter().map(|ws| ws.name).collect())
+    }
+}
+
+
+/// Convert an action in YAML notation to its JSON representation
+///
+/// Tagged values (like `!switch-workspace 2`) become maps with the tag as key.
+fn yaml_to_json(value: serde_yaml::Value) -> serde_json::Value {
+    match value {
+        serde_yaml::Value::Tagged(tagged) => {
+            let tag = tagged.tag.to_string().trim_start_matches('!').to_owned();
+            serde_json::json!({ tag: yaml_to_json(tagged.value) })
+        },
+        serde_yaml::Value::Sequence(seq) => seq.into_iter().map(yaml_to_json).collect(),
+        serde_yaml::Value::Mapping(map) => map.into_iter()
+            .filter_map(|(k, v)| Some((k.as_str()?.to_owned(), yaml_to_json(v))))
+            .collect(),
+        other => serde_json::to_value(other).unwrap_or_default(),
+    }
+}
+
+/// Create the directory of the socket if it is placed in the temporary directory
+///
+/// Fails if the directory already exists, but belongs to another user or is accessible by others.
+pub fn create_socket_dir() -> io::Result<()> {
+    if env::var_os(SOCKET_ENV).is_some() || env::var_os("XDG_RUNTIME_DIR").is_some() {
+        return Ok(());
+    }
+
+    let dir = fallback_dir();
+    match fs::DirBuilder::new().mode(0o700).create(&dir) {
+        Err(e) if e.kind() != io::ErrorKind::AlreadyExists => return Err(e),
+        _ => (),
+    }
+    let metadata = fs::symlink_metadata(&dir)?;
+    if !metadata.is_dir() || metadata.uid() != unsafe { libc::getuid() } || metadata.mode() & 0o077 != 0 {
+        let msg = format!("{} is not a private directory of the current user", dir.to_string_lossy());
+        return Err(io::Error::new(io::ErrorKind::PermissionDenied, msg));
+    }
+    Ok(())
+}
+
+/// Per-user directory in the temporary directory for systems without `$XDG_RUNTIME_DIR`
+fn fallback_dir() -> PathBuf {
+    env::temp_dir().join(format!("marswm-{}", unsafe { libc::getuid() }))
+}
+
+/// Location of the socket for the current X display
+///
+/// The socket is placed in `$XDG_RUNTIME_DIR` (or a per-user directory in the temporary directory)
+/// unless overridden by [SOCKET_ENV].
+pub fn socket_path() -> PathBuf {
+    if let Some(path) = env::var_os(SOCKET_ENV) {
+        return PathBuf::from(path);
+    }
+
+    let display = env::var("DISPLAY").unwrap_or_default().replace('/', "_");
+    let dir = env::var_os("XDG_RUNTIME_DIR").map(PathBuf::from).unwrap_or_else(fallback_dir);
+    dir.join(format!("marswm{}.sock", display))
+}
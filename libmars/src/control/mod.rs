@@ -3,6 +3,8 @@
 use crate::common::error::*;
 
 
+#[cfg(feature = "ipc")]
+pub mod ipc;
 pub mod x11;
 
 
@@ -10,18 +12,101 @@ pub enum SettingMode {
     Set, Unset, Toggle
 }
 
+/// Information about a client as reported by the window manager
+#[derive(Clone,PartialEq,Eq,Debug)]
+#[cfg_attr(feature = "ipc", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClientInfo {
+    pub window: u64,
+    pub title: String,
+    pub class: String,
+    /// global workspace index (`None` for pinned clients)
+    pub workspace: Option<u32>,
+    pub monitor: Option<u32>,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub active: bool,
+    pub fullscreen: bool,
+    pub pinned: bool,
+    pub tiled: bool,
+}
+
+/// Layout of a workspace as reported by the window manager
+#[derive(Clone,PartialEq,Eq,Debug)]
+#[cfg_attr(feature = "ipc", derive(serde::Serialize, serde::Deserialize))]
+pub struct LayoutInfo {
+    pub workspace: u32,
+    pub layout: String,
+}
+
+/// Information about a monitor as reported by the window manager
+#[derive(Clone,PartialEq,Eq,Debug)]
+#[cfg_attr(feature = "ipc", derive(serde::Serialize, serde::Deserialize))]
+pub struct MonitorInfo {
+    pub index: u32,
+    pub name: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    /// global index of the workspace currently shown on the monitor (if known)
+    pub workspace: Option<u32>,
+}
+
+/// Information about a workspace as reported by the window manager
+#[derive(Clone,PartialEq,Eq,Debug)]
+#[cfg_attr(feature = "ipc", derive(serde::Serialize, serde::Deserialize))]
+pub struct WorkspaceInfo {
+    pub index: u32,
+    pub name: String,
+    pub monitor: u32,
+    /// workspace is currently shown on its monitor
+    pub visible: bool,
+    /// workspace is the currently active one
+    pub focused: bool,
+    pub clients: u32,
+}
+
+/// Events published by the window manager
+#[derive(Clone,PartialEq,Eq,Debug)]
+#[cfg_attr(feature = "ipc", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "ipc", serde(tag = "event", rename_all = "kebab-case"))]
+pub enum WMEvent {
+    ClientManaged { window: u64 },
+    ClientUnmanaged { window: u64 },
+    FocusChanged { window: Option<u64> },
+    LayoutChanged { workspace: u32, layout: String },
+    MonitorsChanged,
+    TitleChanged { window: u64, title: String },
+    WorkspaceSwitched { workspace: u32 },
+}
+
 pub trait WMController<W> {
     fn activate_window(&self, window: W) -> Result<()>;
+    /// Names of all layouts the window manager provides
+    fn available_layouts(&self) -> Result<Vec<String>>;
     fn center_window(&self, window: W) -> Result<()>;
+    /// List all clients managed by the window manager
+    fn clients(&self) -> Result<Vec<ClientInfo>>;
     fn close_window(&self, window: W) -> Result<()>;
     fn count_workspaces(&self) -> Result<u32>;
     fn current_workspace(&self) -> Result<u32>;
+    /// Execute an action of the window manager given as text (YAML for marswm)
+    fn execute_action(&self, window: Option<W>, action: &str) -> Result<()>;
     fn fullscreen_window(&self, window: W, mode: SettingMode) -> Result<()>;
     fn get_active_window(&self) -> Result<W>;
     fn get_workspace(&self, window: W) -> Result<u32>;
+    /// Name of the layout used on a workspace
+    fn layout(&self, workspace: u32) -> Result<String>;
+    fn monitors(&self) -> Result<Vec<MonitorInfo>>;
+    /// Let the user select a window by clicking on it
+    fn pick_window(&self) -> Result<W>;
     fn pin_window(&self, window: W, mode: SettingMode) -> Result<()>;
     fn send_window_to_workspace(&self, window: W, workspace: u32) -> Result<()>;
     fn set_status(&self, status: String) -> Result<()>;
+    /// Pass events of the window manager to `handler` until the connection is closed
+    fn subscribe(&self, handler: &mut dyn FnMut(WMEvent)) -> Result<()>;
     fn switch_workspace(&self, workspace: u32) -> Result<()>;
     fn tile_window(&self, window: W, mode: SettingMode) -> Result<()>;
     fn window_is_fullscreen(&self, window: W) -> Result<bool>;
@@ -1,16 +1,25 @@
 extern crate x11;
 
+use std::collections::BTreeSet;
 use std::ptr;
+use std::slice;
 use std::ffi::*;
+use std::mem::MaybeUninit;
+use std::time::{Duration, Instant};
+use x11::keysym;
 use x11::xlib::{self, XDefaultRootWindow};
 
+use crate::common::Dimensioned;
 use crate::common::error::*;
 use crate::common::x11::atoms::X11Atom::*;
 use crate::common::x11::atoms::X11Atom;
-use crate::common::x11::send_client_message;
+use crate::common::x11::{BUTTONMASK, CURSOR_PICK, on_error_dummy, query_monitor_config, send_client_message};
 use crate::common::x11::window::X11Window;
 use crate::control::*;
 
+/// Time the window manager may take to accept an action
+const ACTION_TIMEOUT: Duration = Duration::from_secs(2);
+
 pub type EventHandlerFn = Option<unsafe extern "C" fn(_: *mut xlib::Display, _: *mut xlib::XErrorEvent) -> c_int>;
 
 pub struct X11Controller {
@@ -39,14 +48,73 @@ impl X11Controller {
 }
 
 
+impl X11Controller {
+    /// Find the client that is the given window or one of its descendants (e.g. inside a frame)
+    fn client_in(&self, window: xlib::Window, clients: &BTreeSet<xlib::Window>) -> Option<xlib::Window> {
+        if clients.contains(&window) {
+            return Some(window);
+        }
+
+        let children = unsafe {
+            let mut root: xlib::Window = 0;
+            let mut parent: xlib::Window = 0;
+            let mut children_ptr: *mut xlib::Window = ptr::null_mut();
+            let mut nchildren: u32 = 0;
+            if xlib::XQueryTree(self.display, window, &mut root, &mut parent, &mut children_ptr, &mut nchildren) == 0 {
+                return None;
+            }
+            if children_ptr.is_null() {
+                Vec::new()
+            } else {
+                let children = slice::from_raw_parts(children_ptr, nchildren as usize).to_vec();
+                xlib::XFree(children_ptr as *mut c_void);
+                children
+            }
+        };
+        children.into_iter().find_map(|child| self.client_in(child, clients))
+    }
+
+    fn client_list(&self) -> BTreeSet<xlib::Window> {
+        self.root.x11_read_property_long(self.display, NetClientList, xlib::XA_WINDOW)
+            .map(|list| list.into_iter().collect())
+            .unwrap_or_default()
+    }
+
+    /// Position of a window relative to the root window
+    fn root_position(&self, window: xlib::Window) -> Option<(i32, i32)> {
+        let (mut x, mut y, mut child) = (0, 0, 0);
+        let status = unsafe {
+            xlib::XTranslateCoordinates(self.display, window, self.root, 0, 0, &mut x, &mut y, &mut child)
+        };
+        if status != 0 {
+            Some((x, y))
+        } else {
+            None
+        }
+    }
+
+    fn watch_clients<'a>(&self, clients: impl Iterator<Item = &'a xlib::Window>) {
+        for window in clients {
+            unsafe { xlib::XSelectInput(self.display, *window, xlib::PropertyChangeMask); }
+        }
+    }
+}
+
 impl WMController<xlib::Window> for X11Controller {
     fn activate_window(&self, window: xlib::Window) -> Result<()> {
         require_ewmh_atom(self.display, NetActiveWindow)?;
-        let data = xlib::ClientMessageData::new();
+        let mut data = xlib::ClientMessageData::new();
+        // source indication: requested by a pager (on behalf of the user)
+        data.set_long(0, 2);
         send_client_message(self.display, NetActiveWindow, window, data);
         Ok(())
     }
 
+    fn available_layouts(&self) -> Result<Vec<String>> {
+        // layouts are not part of EWMH
+        Err(MarsError::property_unavailable("layouts"))
+    }
+
     fn center_window(&self, window: xlib::Window) -> Result<()> {
         require_ewmh_atom(self.display, MarsCenter)?;
         let data = xlib::ClientMessageData::new();
@@ -54,6 +122,41 @@ impl WMController<xlib::Window> for X11Controller {
         Ok(())
     }
 
+    fn clients(&self) -> Result<Vec<ClientInfo>> {
+        require_ewmh_atom(self.display, NetClientList)?;
+        let windows = self.root.x11_read_property_long(self.display, NetClientList, xlib::XA_WINDOW)
+            .map_err(MarsError::unknown)?;
+        let active = self.get_active_window().ok();
+        let monitors = query_monitor_config(self.display, true);
+
+        // clients may be destroyed while we are reading their properties
+        unsafe { xlib::XSetErrorHandler(Some(on_error_dummy)); }
+        let clients = windows.iter().filter_map(|window| {
+            let (_, _, _, width, height, _, _) = window.x11_geometry(self.display).ok()?;
+            let (x, y) = self.root_position(*window)?;
+            let center = (x + width as i32 / 2, y + height as i32 / 2);
+            let desktop = window.x11_read_property_long(self.display, NetWMDesktop, xlib::XA_CARDINAL).ok()
+                .and_then(|data| data.first().copied());
+            let state = window.x11_net_wm_state(self.display).unwrap_or_default();
+
+            Some(ClientInfo {
+                window: *window,
+                title: window.x11_wm_name(self.display).unwrap_or_default(),
+                class: window.x11_class_hint(self.display).map(|(_, class)| class).unwrap_or_default(),
+                workspace: desktop.filter(|d| *d != u64::MAX).map(|d| d as u32),
+                monitor: monitors.iter().position(|m| m.contains_point(center)).map(|i| i as u32),
+                x, y, width, height,
+                active: active == Some(*window),
+                fullscreen: state.contains(&NetWMStateFullscreen),
+                pinned: desktop == Some(u64::MAX),
+                tiled: state.contains(&MarsWMStateTiled),
+            })
+        }).collect();
+        unsafe { xlib::XSetErrorHandler(self.event_handler); }
+
+        Ok(clients)
+    }
+
     fn close_window(&self, window: xlib::Window) -> Result<()> {
         if require_ewmh_atom(self.display, NetCloseWindow).is_ok() {
             let data = xlib::ClientMessageData::new();
@@ -85,6 +188,44 @@ impl WMController<xlib::Window> for X11Controller {
             .map_err(|_| MarsError::failed_conversion(ws_u64, stringify!(u64), stringify!(u32)))
     }
 
+    fn execute_action(&self, window: Option<xlib::Window>, action: &str) -> Result<()> {
+        require_ewmh_atom(self.display, MarsAction)?;
+        unsafe {
+            // the action does not fit into a client message, so it is passed in a property of a temporary window
+            let source = xlib::XCreateSimpleWindow(self.display, self.root, 0, 0, 1, 1, 0, 0, 0);
+            xlib::XSelectInput(self.display, source, xlib::PropertyChangeMask);
+            source.x11_set_text_list_property(self.display, MarsAction, &[action.to_owned()]);
+
+            let mut data = xlib::ClientMessageData::new();
+            data.set_long(0, source as i64);
+            send_client_message(self.display, MarsAction, window.unwrap_or(0), data);
+
+            // wait for the window manager to take the action, as the window is gone once we disconnect
+            let atom = MarsAction.to_xlib_atom(self.display);
+            let deadline = Instant::now() + ACTION_TIMEOUT;
+            let result = loop {
+                if xlib::XPending(self.display) == 0 {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    if remaining.is_zero() || !wait_readable(xlib::XConnectionNumber(self.display), remaining) {
+                        break Err(MarsError::failed_request("waiting for the window manager to accept the action"));
+                    }
+                    continue;
+                }
+
+                let mut event: MaybeUninit<xlib::XEvent> = MaybeUninit::uninit();
+                xlib::XNextEvent(self.display, event.as_mut_ptr());
+                let event = event.assume_init();
+                if event.get_type() == xlib::PropertyNotify && event.property.window == source
+                        && event.property.atom == atom && event.property.state == xlib::PropertyDelete {
+                    break Ok(());
+                }
+            };
+            xlib::XDestroyWindow(self.display, source);
+            xlib::XFlush(self.display);
+            result
+        }
+    }
+
     fn fullscreen_window(&self, window: xlib::Window, mode: SettingMode) -> Result<()> {
         require_ewmh_atom(self.display, NetWMState)?;
         require_ewmh_atom(self.display, NetWMStateFullscreen)?;
@@ -118,6 +259,80 @@ impl WMController<xlib::Window> for X11Controller {
         }
     }
 
+    fn layout(&self, _workspace: u32) -> Result<String> {
+        // layouts are not part of EWMH
+        Err(MarsError::property_unavailable("layout"))
+    }
+
+    fn monitors(&self) -> Result<Vec<MonitorInfo>> {
+        let monitors = query_monitor_config(self.display, true).iter().enumerate().map(|(i, config)| {
+            let dimensions = config.dimensions();
+            MonitorInfo {
+                index: i as u32,
+                name: config.name().to_owned(),
+                x: dimensions.x(),
+                y: dimensions.y(),
+                width: dimensions.w(),
+                height: dimensions.h(),
+                workspace: None,
+            }
+        }).collect();
+        Ok(monitors)
+    }
+
+    fn pick_window(&self) -> Result<xlib::Window> {
+        require_ewmh_atom(self.display, NetClientList)?;
+        let picked = unsafe {
+            let cursor = xlib::XCreateFontCursor(self.display, CURSOR_PICK);
+            let status = xlib::XGrabPointer(self.display, self.root, xlib::False, BUTTONMASK as u32, xlib::GrabModeAsync,
+                                            xlib::GrabModeAsync, self.root, cursor, xlib::CurrentTime);
+            if status != xlib::GrabSuccess {
+                xlib::XFreeCursor(self.display, cursor);
+                return Err(MarsError::failed_request("XGrabPointer"));
+            }
+            // the keyboard is grabbed to be able to cancel with escape
+            let status = xlib::XGrabKeyboard(self.display, self.root, xlib::False, xlib::GrabModeAsync,
+                                             xlib::GrabModeAsync, xlib::CurrentTime);
+            if status != xlib::GrabSuccess {
+                xlib::XUngrabPointer(self.display, xlib::CurrentTime);
+                xlib::XFreeCursor(self.display, cursor);
+                return Err(MarsError::failed_request("XGrabKeyboard"));
+            }
+
+            // wait for the button to be released again, so the click does not end up in the window
+            let mut pressed = None;
+            loop {
+                let mut event: MaybeUninit<xlib::XEvent> = MaybeUninit::uninit();
+                xlib::XMaskEvent(self.display, BUTTONMASK | xlib::KeyPressMask, event.as_mut_ptr());
+                let mut event = event.assume_init();
+                match event.get_type() {
+                    xlib::ButtonPress => pressed = Some(event.button.subwindow),
+                    xlib::ButtonRelease if pressed.is_some() => break,
+                    xlib::KeyPress if xlib::XLookupKeysym(&mut event.key, 0) == keysym::XK_Escape as u64 => {
+                        pressed = None;
+                        break;
+                    },
+                    _ => (),
+                }
+            }
+
+            xlib::XUngrabKeyboard(self.display, xlib::CurrentTime);
+            xlib::XUngrabPointer(self.display, xlib::CurrentTime);
+            xlib::XFreeCursor(self.display, cursor);
+            pressed.unwrap_or(0)
+        };
+
+        // clicks on the root window have no subwindow
+        if picked == 0 {
+            return Err(MarsError::invalid_input("no client selected"));
+        }
+
+        // the window under the pointer is usually the frame of the client, which may be gone already
+        unsafe { xlib::XSetErrorHandler(Some(on_error_dummy)); }
+        self.client_in(picked, &self.client_list())
+            .ok_or(MarsError::invalid_input("no client selected"))
+    }
+
     fn pin_window(&self, window: xlib::Window, mode: SettingMode) -> Result<()> {
         require_ewmh_atom(self.display, NetWMDesktop)?;
         match mode {
@@ -157,6 +372,64 @@ impl WMController<xlib::Window> for X11Controller {
         Ok(())
     }
 
+    fn subscribe(&self, handler: &mut dyn FnMut(WMEvent)) -> Result<()> {
+        unsafe {
+            // clients may be destroyed before we get to watch them
+            xlib::XSetErrorHandler(Some(on_error_dummy));
+            xlib::XSelectInput(self.display, self.root, xlib::PropertyChangeMask);
+        }
+
+        let mut clients = self.client_list();
+        self.watch_clients(clients.iter());
+        let mut active = self.get_active_window().ok();
+
+        loop {
+            let event = unsafe {
+                let mut event: MaybeUninit<xlib::XEvent> = MaybeUninit::uninit();
+                xlib::XNextEvent(self.display, event.as_mut_ptr());
+                event.assume_init()
+            };
+            if event.get_type() != xlib::PropertyNotify {
+                continue;
+            }
+
+            let event = unsafe { event.property };
+            let atom = match X11Atom::from_xlib_atom(self.display, event.atom) {
+                Some(atom) => atom,
+                None => continue,
+            };
+
+            if event.window == self.root {
+                match atom {
+                    NetActiveWindow => {
+                        let window = self.get_active_window().ok().filter(|w| *w != 0);
+                        if window != active {
+                            active = window;
+                            handler(WMEvent::FocusChanged { window });
+                        }
+                    },
+                    NetClientList => {
+                        let new_clients = self.client_list();
+                        self.watch_clients(new_clients.difference(&clients));
+                        for window in new_clients.difference(&clients) {
+                            handler(WMEvent::ClientManaged { window: *window });
+                        }
+                        for window in clients.difference(&new_clients) {
+                            handler(WMEvent::ClientUnmanaged { window: *window });
+                        }
+                        clients = new_clients;
+                    },
+                    NetCurrentDesktop => handler(WMEvent::WorkspaceSwitched { workspace: self.current_workspace()? }),
+                    NetDesktopGeometry | NetNumberOfDesktops | NetWorkarea => handler(WMEvent::MonitorsChanged),
+                    _ => (),
+                }
+            } else if clients.contains(&event.window) && (atom == WMName || atom == NetWMName) {
+                let title = event.window.x11_wm_name(self.display).unwrap_or_default();
+                handler(WMEvent::TitleChanged { window: event.window, title });
+            }
+        }
+    }
+
     fn switch_workspace(&self, workspace: u32) -> Result<()> {
         require_ewmh_atom(self.display, NetCurrentDesktop)?;
         let mut data = xlib::ClientMessageData::new();
@@ -231,3 +504,10 @@ fn require_ewmh_atom(display: *mut xlib::Display, atom: X11Atom) -> Result<()> {
         Err(MarsError::x11_unsupported_atom(atom))
     }
 }
+
+/// Wait until data is available on the X connection or the timeout expires
+fn wait_readable(fd: c_int, timeout: Duration) -> bool {
+    let mut pollfd = libc::pollfd { fd, events: libc::POLLIN, revents: 0 };
+    let timeout_ms = timeout.as_millis().clamp(1, c_int::MAX as u128) as c_int;
+    unsafe { libc::poll(&mut pollfd, 1, timeout_ms) > 0 }
+}
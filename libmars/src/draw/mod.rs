@@ -6,6 +6,17 @@ use crate::common::error::*;
 
 pub mod x11;
 
+/// Horizontal alignment of text
+#[derive(Clone,Copy,PartialEq,Eq,Debug,Default)]
+#[cfg_attr(feature = "configuration", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "configuration", serde(rename_all = "kebab-case"))]
+pub enum TextAlignment {
+    Left,
+    #[default]
+    Center,
+    Right,
+}
+
 pub trait Canvas {
     fn dimensions(&self) -> Dimensions;
     fn draw_line(&mut self, pt1: (i32, i32), pt2: (i32, i32));
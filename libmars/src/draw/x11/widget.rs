@@ -11,6 +11,7 @@ use crate::draw::x11::canvas::X11Canvas;
 
 pub const MIN_SIZE: (u32, u32) = (10, 10);
 pub const MAX_SIZE: (u32, u32) = (u32::MAX, u32::MAX);
+const ELLIPSIS: &str = "…";
 
 
 pub trait WidgetEventHandler {
@@ -63,6 +64,7 @@ pub struct X11TextWidget {
     vpad: u32,
     fg_color: u64,
     bg_color: u64,
+    alignment: TextAlignment,
 }
 
 impl<W: Widget> X11FlowLayoutWidget<W> {
@@ -133,6 +135,13 @@ impl<W: Widget> X11FlowLayoutWidget<W> {
         self.arrange_children();
     }
 
+    pub fn set_background(&mut self, color: u64) -> Result<()> {
+        self.canvas.set_foreground(color)?;
+        self.canvas.set_background(color)?;
+        self.redraw();
+        Ok(())
+    }
+
     pub fn remove(&mut self, index: usize) {
         self.children.remove(index);
     }
@@ -189,6 +198,7 @@ impl X11TextWidget {
             max_size: MAX_SIZE,
             hpad, vpad,
             fg_color, bg_color,
+            alignment: TextAlignment::default(),
         };
 
         widget.resize_to_content();
@@ -236,11 +246,34 @@ impl X11TextWidget {
         Ok(())
     }
 
+    pub fn set_alignment(&mut self, alignment: TextAlignment) {
+        self.alignment = alignment;
+        self.redraw();
+    }
+
     pub fn set_label(&mut self, label: String) {
         self.label = label;
         self.resize_to_content();
         self.redraw();
     }
+
+    /// Shorten the label with an ellipsis until it fits into the given width
+    fn truncated_label(&self, width: u32) -> String {
+        let mut label = self.label.clone();
+        match self.canvas.text_size(&label) {
+            Ok((tw, _)) if tw > width => (),
+            _ => return label,
+        }
+
+        while label.pop().is_some() {
+            let truncated = format!("{}{}", label.trim_end(), ELLIPSIS);
+            match self.canvas.text_size(&truncated) {
+                Ok((tw, _)) if tw > width => continue,
+                _ => return truncated,
+            }
+        }
+        label
+    }
 }
 
 impl<W: Widget> Widget for X11FlowLayoutWidget<W> {
@@ -325,11 +358,15 @@ impl Widget for X11TextWidget {
 
     fn redraw(&mut self) {
         self.canvas.fill_rectangle_with(0, 0, self.width, self.height, self.bg_color);
-        if let Ok(text_size) = self.canvas.text_size(&self.label) {
-            // center text if possible
+        let label = self.truncated_label(self.width.saturating_sub(2 * self.hpad));
+        if let Ok(text_size) = self.canvas.text_size(&label) {
             // the min() call is necessary as the text_size might be bigger than the max size
-            let x = (self.width - cmp::min(self.width, text_size.0)) / 2;
-            let _ = self.canvas.draw_text(x as i32, self.vpad as i32, self.height - 2*self.vpad, &self.label);
+            let x = match self.alignment {
+                TextAlignment::Left => self.hpad,
+                TextAlignment::Center => (self.width - cmp::min(self.width, text_size.0)) / 2,
+                TextAlignment::Right => self.width.saturating_sub(text_size.0 + self.hpad),
+            };
+            let _ = self.canvas.draw_text(x as i32, self.vpad as i32, self.height - 2*self.vpad, &label);
         } else {
             // otherwise just align to the left
             let _ = self.canvas.draw_text(self.hpad as i32, self.vpad as i32, self.height - 2*self.vpad, &self.label);
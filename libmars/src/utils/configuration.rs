@@ -38,14 +38,27 @@ pub fn read_file<T: for<'a> Deserialize<'a>>(path: &path::Path) -> Result<T, Str
     }
 }
 
-/// Read config file
+/// Parse a configuration value from a string (like an action passed on the command line)
+pub fn read_str<T: for<'a> Deserialize<'a>>(string: &str) -> Result<T, String> {
+    serde_yaml::from_str(string).map_err(|e| e.to_string())
+}
+
+/// Find the path of a config file
 ///
 /// * `config_name` - The name of the applications configuration subdirectory
 /// * `file_name` - The file name (with extension)
-pub fn read_config_file<T: for<'a> Deserialize<'a>>(config_name: &str, file_name: &str) -> Result<T, String> {
+pub fn find_config_file(config_name: &str, file_name: &str) -> Result<Option<path::PathBuf>, String> {
     let config_dir = xdg::BaseDirectories::with_prefix(config_name)
         .map_err(|e| format!("unable to open config dir ({})", e))?;
-    if let Some(path) = config_dir.find_config_file(file_name) {
+    Ok(config_dir.find_config_file(file_name))
+}
+
+/// Read config file
+///
+/// * `config_name` - The name of the applications configuration subdirectory
+/// * `file_name` - The file name (with extension)
+pub fn read_config_file<T: for<'a> Deserialize<'a>>(config_name: &str, file_name: &str) -> Result<T, String> {
+    if let Some(path) = find_config_file(config_name, file_name)? {
         deserialize_file(&path).map_err(|(_, msg)| msg)
     } else {
         Err(format!("configuration {} not found", file_name))
@@ -0,0 +1,54 @@
+//! Color theme shared between the window manager and the bar.
+//!
+//! The theme file contains a map of named colors, which may then be used in place of color values
+//! in the configuration files of all applications.
+
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+
+use crate::common::color::*;
+use crate::utils::configuration::*;
+
+
+pub const THEME_CONFIG_DIR: &str = "marswm";
+pub const THEME_FILE: &str = "theme.yaml";
+
+
+#[derive(Serialize,Deserialize,PartialEq,Debug,Clone,Default)]
+#[serde(default)]
+pub struct Theme {
+    /// named colors
+    pub colors: BTreeMap<String, Color>,
+}
+
+
+impl Theme {
+    /// Make the colors of this theme available by name
+    pub fn apply(&self) {
+        set_theme_colors(self.colors.iter().map(|(name, color)| (name.clone(), color.pixel())).collect());
+    }
+}
+
+
+/// Read the X resources and the theme file and make its colors available by name
+///
+/// X11 color names in the theme are looked up with `color_by_name` (see [Color::resolve]).
+/// The theme is applied even if some of its colors cannot be resolved, but they are reported as
+/// error. A missing theme file is not considered an error.
+pub fn load_theme(color_by_name: &dyn Fn(&str) -> Option<u64>) -> Result<Theme, String> {
+    load_xresources();
+    let mut theme: Theme = match find_config_file(THEME_CONFIG_DIR, THEME_FILE)? {
+        Some(path) => read_file(&path)?,
+        None => Theme::default(),
+    };
+    let problems: Vec<String> = theme.colors.values_mut()
+        .filter_map(|color| color.resolve(color_by_name).err())
+        .collect();
+    theme.apply();
+
+    if problems.is_empty() {
+        Ok(theme)
+    } else {
+        Err(problems.join(", "))
+    }
+}
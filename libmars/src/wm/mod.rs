@@ -5,6 +5,7 @@ use std::rc::Rc;
 
 use crate::common::*;
 use crate::common::error::*;
+use crate::draw::TextAlignment;
 
 pub mod x11;
 
@@ -12,21 +13,40 @@ pub trait WindowManager<B: Backend<A>, A> {
     fn active_client(&self) -> Option<Rc<RefCell<B::Client>>>;
     fn active_workspace(&self, backend: &mut B) -> u32;
     fn activate_client(&mut self, backend: &mut B, client_rc: Rc<RefCell<B::Client>>);
+    /// Handle a request by an application to activate one of its clients
+    fn activation_request(&mut self, backend: &mut B, client_rc: Rc<RefCell<B::Client>>);
     fn center_client(&mut self, backend: &mut B, client_rc: Rc<RefCell<B::Client>>);
     fn client_switches_monitor(&mut self, client_rc: Rc<RefCell<B::Client>>, monitor: u32);
     fn clients(&self) -> Box<dyn Iterator<Item = &Rc<RefCell<B::Client>>> + '_>;
+    /// Frame extents (north, east, south, west) a new client would get when managed
+    fn default_frame_extents(&self) -> (u32, u32, u32, u32);
+    /// Execute an action sent as text by an external program
+    fn execute_action(&mut self, backend: &mut B, action: &str, client_option: Option<Rc<RefCell<B::Client>>>);
     fn focus_client(&mut self, backend: &mut B, client_option: Option<Rc<RefCell<B::Client>>>);
+    /// Whether entering a window with the pointer should focus it
+    fn focus_follows_pointer(&self) -> bool;
     fn fullscreen_client(&mut self, backend: &mut B, client_rc: Rc<RefCell<B::Client>>, state: bool);
     fn handle_button(&mut self, backend: &mut B, modifiers: u32, button: u32, target: ButtonTarget,
                      client_option: Option<Rc<RefCell<B::Client>>>);
-    fn handle_key(&mut self, backend: &mut B, modifiers: u32, key: u32, client_option: Option<Rc<RefCell<B::Client>>>);
+    /// Handle requests queued by external programs (e.g. over an IPC socket)
+    fn handle_requests(&mut self, backend: &mut B);
+    fn handle_key(&mut self, backend: &mut B, modifiers: u32, keycode: u32, keysym: u32, release: bool,
+                  client_option: Option<Rc<RefCell<B::Client>>>);
+    /// Handle the release of a modifier key while the keyboard is grabbed
+    ///
+    /// * `modifiers` - modifiers that are still held down
+    fn handle_modifier_release(&mut self, backend: &mut B, modifiers: u32);
     fn manage(&mut self, backend: &mut B, client_rc: Rc<RefCell<B::Client>>, workspace_preference: Option<u32>);
     fn move_request(&mut self, backend: &mut B, client_rc: Rc<RefCell<B::Client>>, x: i32, y: i32) -> bool;
     fn move_to_workspace(&mut self, backend: &mut B, client_rc: Rc<RefCell<B::Client>>, workspace_idx: u32);
     fn resize_request(&mut self, backend: &mut B, client_rc: Rc<RefCell<B::Client>>, width: u32, height: u32) -> bool;
+    /// Called when the X resources (e.g. colors set with `xrdb`) have changed
+    fn resources_changed(&mut self, backend: &mut B);
     fn set_client_pinned(&mut self, backend: &mut B, client_rc: Rc<RefCell<B::Client>>, state: bool);
     fn switch_workspace(&mut self, backend: &mut B, workspace_idx: u32);
     fn tile_client(&mut self, backend: &mut B, client_rc: Rc<RefCell<B::Client>>, state: bool);
+    /// Called when the title of a client has changed
+    fn title_changed(&mut self, backend: &mut B, client_rc: Rc<RefCell<B::Client>>);
     fn toggle_fullscreen_client(&mut self, backend: &mut B, client_rc: Rc<RefCell<B::Client>>);
     fn toggle_tile_client(&mut self, backend: &mut B, client_rc: Rc<RefCell<B::Client>>);
     fn unmanage(&mut self, backend: &mut B, client_rc: Rc<RefCell<B::Client>>);
@@ -34,41 +54,52 @@ pub trait WindowManager<B: Backend<A>, A> {
 }
 
 pub trait Client<A>: Eq + Dimensioned {
-    fn add_title(&mut self, font: &str, hpad: u32, vpad: u32, color: u64) -> Result<()>;
+    fn add_title(&mut self, font: &str, hpad: u32, vpad: u32, color: u64, alignment: TextAlignment,
+                 format: &str) -> Result<()>;
+    fn add_title_buttons(&mut self, font: &str, hpad: u32, vpad: u32, color: u64, labels: &[String]) -> Result<()>;
     fn application(&self) -> String;
     fn attributes(&self) -> &A;
     fn attributes_mut(&mut self) -> &mut A;
     fn bind_button(&mut self, modifiers: u32, button: u32, target: ButtonTarget);
-    fn bind_key(&mut self, modifiers: u32, key: u32);
+    fn bind_key(&mut self, modifiers: u32, key: KeyIdentifier);
     fn center_on_screen(&mut self, dimensions: Dimensions);
     fn close(&self);
     fn dont_decorate(&self) -> bool;
+    fn export_allowed_actions(&self, actions: &[ClientAction]);
     fn export_pinned(&self, state: bool, workspace_idx: Option<u32>);
     fn export_tiled(&self, state: bool);
     fn export_workspace(&self, workspace_idx: u32);
     fn frame_width(&self) -> (u32, u32, u32, u32);
     fn hide(&mut self);
+    /// Unique identifier of the client (the window id for X11)
+    fn id(&self) -> u64;
     fn inner_bw(&self) -> u32;
     // dimensions excluding all borders
     fn inner_dimensions(&self) -> Dimensions;
     fn is_centered_on_screen(&self, dimensions: Dimensions) -> bool;
     fn is_dialog(&self) -> bool;
     fn is_fullscreen(&self) -> bool;
+    fn is_urgent(&self) -> bool;
     fn is_visible(&self) -> bool;
     fn move_resize(&mut self, x: i32, y: i32, width: u32, height: u32);
     fn name(&self) -> &str;
     fn outer_bw(&self) -> u32;
     fn raise(&self);
     fn set_dimensions(&mut self, dimensions: Dimensions);
+    /// Intercept clicks on the client window, so it can be focused by clicking on it
+    fn set_focus_on_click(&mut self, state: bool);
     fn set_frame_color(&mut self, color: u64);
     fn set_frame_width(&mut self, width: (u32, u32, u32, u32));
     fn set_fullscreen(&mut self, monitor_conf: &MonitorConfig);
     fn set_height(&mut self, height: u32);
     fn set_inner_bw(&mut self, bw: u32);
     fn set_inner_color(&mut self, color: u64);
+    /// Set opacity (0.0 - 1.0) relative to the opacity requested by the client itself
+    fn set_opacity(&mut self, opacity: f32);
     fn set_outer_bw(&mut self, bw: u32);
     fn set_outer_color(&mut self, color: u64);
     fn set_title_color(&mut self, color: u64);
+    fn set_urgent(&mut self, state: bool);
     fn show(&mut self);
     fn title(&self) -> String;
     fn total_bw(&self) -> (u32, u32, u32, u32);
@@ -83,12 +114,18 @@ pub trait Backend<A> {
     /// Associated client type
     type Client: Client<A>;
 
+    /// Look up a color name of the windowing system (like `DarkSlateGray`)
+    fn color_by_name(&self, name: &str) -> Option<u64>;
+
     /// Make active window information available to clients
     fn export_active_window(&self, client_option: &Option<Rc<RefCell<Self::Client>>>);
 
     /// Make client list information available to clients
     fn export_client_list(&self, clients: Vec<&Rc<RefCell<Self::Client>>>, clients_stacked: Vec<&Rc<RefCell<Self::Client>>>);
 
+    /// Make name of the active binding mode available to clients
+    fn export_binding_mode(&self, mode: &str);
+
     /// Make currently active workspace available to clients
     fn export_current_workspace(&self, workspace_idx: u32);
 
@@ -98,6 +135,12 @@ pub trait Backend<A> {
     /// Get monitor configuration
     fn get_monitor_config(&self) -> Vec<MonitorConfig>;
 
+    /// Bind key on the root window, so it works regardless of the focused client
+    fn bind_key(&mut self, modifiers: u32, key: KeyIdentifier);
+
+    /// Grab keyboard to receive all key presses
+    fn grab_keyboard(&mut self) -> bool;
+
     /// Handle windows existing before initialization
     fn handle_existing_windows(&mut self, wm: &mut dyn WindowManager<Self, A>);
 
@@ -106,6 +149,9 @@ pub trait Backend<A> {
                     action: MouseActionFn<Self, WM, Self::Client>)
         where Self: Sized;
 
+    /// Modifiers that are currently held down
+    fn modifier_state(&self) -> u32;
+
     /// Move client with mouse
     fn mouse_move(&mut self, wm: &mut dyn WindowManager<Self, A>, client_rc: Rc<RefCell<Self::Client>>);
 
@@ -123,6 +169,9 @@ pub trait Backend<A> {
     /// Set client that receives mouse and keyboard inputs
     fn set_input_focus(&self, client_rc: Rc<RefCell<Self::Client>>);
 
+    /// Release keyboard grabbed with [Backend::grab_keyboard]
+    fn ungrab_keyboard(&mut self);
+
     fn warp_pointer(&self, x: i32, y: i32);
 
     fn shutdown(&mut self);
@@ -136,4 +185,24 @@ pub enum ButtonTarget {
     Window,
     Root,
     Frame,
+    Title,
+    /// button in the title bar (index in the list of title buttons)
+    TitleButton(u32),
+}
+
+/// Identifies a key by its symbol or by its layout independent code
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub enum KeyIdentifier {
+    Keysym(u32),
+    Keycode(u32),
+}
+
+/// Actions a window manager allows to be performed on a client
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub enum ClientAction {
+    ChangeWorkspace,
+    Close,
+    Fullscreen,
+    Move,
+    Resize,
 }
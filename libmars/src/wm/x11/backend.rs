@@ -47,6 +47,8 @@ pub struct X11Backend<A: PartialEq> {
     monitors: Vec<MonitorConfig>,
     wmcheck_win: u64,
     unmanaged_clients: Vec<UnmanagedClient>,
+    moveresize_direction: (i32, i32),
+    keyboard_grabbed: bool,
 }
 
 struct XRandrInfo {
@@ -57,19 +59,41 @@ struct XRandrInfo {
 
 
 const MAX_FRAMERATE: u64 = 120;
-const SUPPORTED_ATOMS: &[X11Atom; 22] = & [
+
+// directions of _NET_WM_MOVERESIZE that are not an edge
+const NET_WM_MOVERESIZE_MOVE: i64 = 8;
+const NET_WM_MOVERESIZE_SIZE_KEYBOARD: i64 = 9;
+const NET_WM_MOVERESIZE_MOVE_KEYBOARD: i64 = 10;
+
+// flags of _NET_MOVERESIZE_WINDOW indicating which values are present
+const NET_MOVERESIZE_WINDOW_X: i64 = 1 << 8;
+const NET_MOVERESIZE_WINDOW_Y: i64 = 1 << 9;
+const NET_MOVERESIZE_WINDOW_WIDTH: i64 = 1 << 10;
+const NET_MOVERESIZE_WINDOW_HEIGHT: i64 = 1 << 11;
+const SUPPORTED_ATOMS: &[X11Atom; 34] = & [
     NetActiveWindow,
     NetClientList,
     NetClientListStacking,
     NetCloseWindow,
     NetCurrentDesktop,
     NetDesktopNames,
+    NetFrameExtents,
+    NetMoveresizeWindow,
     NetNumberOfDesktops,
+    NetRequestFrameExtents,
     NetSupported,
     NetSupportingWMCheck,
+    NetWMActionChangeDesktop,
+    NetWMActionClose,
+    NetWMActionFullscreen,
+    NetWMActionMove,
+    NetWMActionResize,
+    NetWMAllowedActions,
     NetWMDesktop,
+    NetWMMoveresize,
     NetWMName,
     NetWMState,
+    NetWMStateDemandsAttention,
     NetWMStateFullscreen,
     NetWMWindowType,
     NetWMWindowTypeDesktop,
@@ -79,6 +103,7 @@ const SUPPORTED_ATOMS: &[X11Atom; 22] = & [
     NetWMWindowTypeNotification,
     NetWorkarea,
 
+    MarsAction,
     MarsCenter,
     MarsWMStateTiled,
 ];
@@ -105,6 +130,8 @@ impl<A: PartialEq + Default> X11Backend<A> {
                 monitors: Vec::new(),
                 wmcheck_win: 0,
                 unmanaged_clients: Vec::new(),
+                moveresize_direction: (1, 1),
+                keyboard_grabbed: false,
             };
 
             // For debugging:
@@ -126,7 +153,8 @@ impl<A: PartialEq + Default> X11Backend<A> {
             let mut attributes: MaybeUninit<xlib::XSetWindowAttributes> = MaybeUninit::uninit();
             (*attributes.as_mut_ptr()).cursor = xlib::XCreateFontCursor(display, CURSOR_NORMAL);
             (*attributes.as_mut_ptr()).event_mask = xlib::SubstructureRedirectMask |
-                xlib::SubstructureNotifyMask | xlib::StructureNotifyMask | xlib::KeyPressMask | xlib::ButtonPressMask;
+                xlib::SubstructureNotifyMask | xlib::StructureNotifyMask | xlib::KeyPressMask | xlib::ButtonPressMask
+                | xlib::PropertyChangeMask;
             xlib::XChangeWindowAttributes(display, root, xlib::CWEventMask | xlib::CWCursor, attributes.as_mut_ptr());
             xlib::XSync(display, xlib::False);
             xlib::XSetErrorHandler(Some(on_error));
@@ -164,6 +192,42 @@ impl<A: PartialEq + Default> X11Backend<A> {
         }
     }
 
+    /// Move and resize a client to the requested inner dimensions as far as the window manager permits
+    fn configure_client(&mut self, wm: &mut (impl WindowManager<Self, A> + ?Sized), client_rc: Rc<RefCell<X11Client<A>>>,
+                        x: i32, y: i32, width: u32, height: u32) {
+        let inner = client_rc.borrow().inner_dimensions();
+        let mut window_changed = false;
+        let (bw_north, bw_east, bw_south, bw_west) = client_rc.borrow().total_bw();
+
+        // issue move request if size is different
+        if x != inner.x() || y != inner.y() {
+            let client = client_rc.borrow();
+            // subtract border to size
+            let x = x - bw_east as i32;
+            let y = y - bw_north as i32;
+            drop(client);
+            // note that only moving might not generate a real ConfigureNotify
+            // therefore we ignore the result of a move_request
+            // window_changed |= wm.move_request(self, client_rc, x, y);
+            wm.move_request(self, client_rc.clone(), x, y);
+        }
+
+        // issue resize request if size is different
+        if width != inner.w() || height != inner.h() {
+            let client = client_rc.borrow();
+            // add border to size
+            let width = width + bw_east + bw_west;
+            let height = height + bw_north + bw_south;
+            drop(client);
+            window_changed |= wm.resize_request(self, client_rc.clone(), width, height);
+        }
+
+        // send synthetic ConfigureNotify if the dimensions were not changed
+        if !window_changed {
+            self.send_configure_notify(client_rc);
+        }
+    }
+
     fn send_configure_notify(&self, client_rc: Rc<RefCell<X11Client<A>>>) {
         let client = client_rc.borrow();
         let inner_dimensions = client.inner_dimensions();
@@ -205,7 +269,7 @@ impl<A: PartialEq + Default> X11Backend<A> {
                 xlib::DestroyNotify => self.on_destroy_notify(wm, event.destroy_window),
                 xlib::EnterNotify => self.on_enter_notify(wm, event.crossing),
                 xlib::Expose => self.on_expose_event(wm, event.expose),
-                xlib::KeyPress => self.on_key_press(wm, event.key),
+                xlib::KeyPress | xlib::KeyRelease => self.on_key_event(wm, event.key),
                 xlib::LeaveNotify => self.on_leave_notify(wm, event.crossing),
                 xlib::MapRequest => self.on_map_request(wm, event.map_request),
                 xlib::MapNotify => self.on_map_notify(wm, event.map),
@@ -328,17 +392,44 @@ impl<A: PartialEq + Default> X11Backend<A> {
         client_rc.borrow_mut().move_resize(pos.0, pos.1, dest_w, dest_h);
     }
 
+    fn mouse_action_resize_edge(&mut self, wm: &mut (impl WindowManager<Self, A> + ?Sized), client_rc: &Rc<RefCell<X11Client<A>>>,
+                                orig_client_pos: (i32, i32), orig_client_size: (u32, u32), delta: (i32, i32)) {
+        let (dir_x, dir_y) = self.moveresize_direction;
+        let dest_w = (orig_client_size.0 as i32 + dir_x * delta.0).max(WINDOW_MIN_SIZE as i32);
+        let dest_h = (orig_client_size.1 as i32 + dir_y * delta.1).max(WINDOW_MIN_SIZE as i32);
+
+        // keep the opposite edge in place when dragging the top or left edge
+        let dest_x = if dir_x < 0 { orig_client_pos.0 + orig_client_size.0 as i32 - dest_w } else { orig_client_pos.0 };
+        let dest_y = if dir_y < 0 { orig_client_pos.1 + orig_client_size.1 as i32 - dest_h } else { orig_client_pos.1 };
+
+        if wm.resize_request(self, client_rc.clone(), dest_w as u32, dest_h as u32) && (dir_x < 0 || dir_y < 0) {
+            wm.move_request(self, client_rc.clone(), dest_x, dest_y);
+        }
+    }
+
     fn on_button_press(&mut self, wm: &mut (impl WindowManager<Self, A> + ?Sized), event: xlib::XButtonEvent) {
         //print_event!(wm, event);
         let modifiers = sanitize_modifiers(event.state);
 
         if let Some(client_rc) = Self::client_by_frame(wm, event.window) {
-            wm.handle_button(self, modifiers, event.button, ButtonTarget::Frame, Some(client_rc));
+            let target = if client_rc.borrow().title_window() == Some(event.window) {
+                ButtonTarget::Title
+            } else if let Some(index) = client_rc.borrow().title_button_index(event.window) {
+                ButtonTarget::TitleButton(index)
+            } else {
+                ButtonTarget::Frame
+            };
+            wm.handle_button(self, modifiers, event.button, target, Some(client_rc));
         } else if let Some(client_rc) = Self::client_by_window(wm, event.window) {
             wm.handle_button(self, modifiers, event.button, ButtonTarget::Window, Some(client_rc));
         } else if event.window == self.root {
             wm.handle_button(self, modifiers, event.button, ButtonTarget::Root, None);
         }
+
+        // pass on clicks intercepted for click to focus
+        unsafe {
+            xlib::XAllowEvents(self.display, xlib::ReplayPointer, xlib::CurrentTime);
+        }
     }
 
     fn on_client_message(&mut self, wm: &mut (impl WindowManager<Self, A> + ?Sized), event: xlib::XClientMessageEvent) {
@@ -347,7 +438,12 @@ impl<A: PartialEq + Default> X11Backend<A> {
             match atom {
                 NetActiveWindow => {
                     if let Some(client_rc) = Self::client_by_window(wm, event.window){
-                        wm.activate_client(self, client_rc);
+                        // source indication: 1 for applications, 2 for pagers and 0 for legacy clients
+                        if event.data.get_long(0) == 2 {
+                            wm.activate_client(self, client_rc);
+                        } else {
+                            wm.activation_request(self, client_rc);
+                        }
                     }
                 },
                 NetCloseWindow => {
@@ -362,6 +458,42 @@ impl<A: PartialEq + Default> X11Backend<A> {
                         wm.switch_workspace(self, workspace);
                     }
                 },
+                NetMoveresizeWindow => {
+                    if let Some(client_rc) = Self::client_by_window(wm, event.window) {
+                        let flags = event.data.get_long(0);
+                        let inner = client_rc.borrow().inner_dimensions();
+                        let x = if flags & NET_MOVERESIZE_WINDOW_X != 0 {
+                            event.data.get_long(1) as i32
+                        } else {
+                            inner.x()
+                        };
+                        let y = if flags & NET_MOVERESIZE_WINDOW_Y != 0 {
+                            event.data.get_long(2) as i32
+                        } else {
+                            inner.y()
+                        };
+                        let width = if flags & NET_MOVERESIZE_WINDOW_WIDTH != 0 {
+                            event.data.get_long(3) as u32
+                        } else {
+                            inner.w()
+                        };
+                        let height = if flags & NET_MOVERESIZE_WINDOW_HEIGHT != 0 {
+                            event.data.get_long(4) as u32
+                        } else {
+                            inner.h()
+                        };
+                        self.configure_client(wm, client_rc, x, y, width, height);
+                    }
+                },
+                NetRequestFrameExtents => {
+                    // windows usually ask for this before being mapped, so we have to estimate
+                    let (north, east, south, west) = match Self::client_by_window(wm, event.window) {
+                        Some(client_rc) => client_rc.borrow().total_bw(),
+                        None => wm.default_frame_extents(),
+                    };
+                    let data = [west.into(), east.into(), north.into(), south.into()];
+                    event.window.x11_replace_property_long(self.display, NetFrameExtents, xlib::XA_CARDINAL, &data);
+                },
                 NetWMDesktop => {
                     if let Some(client_rc) = Self::client_by_window(wm, event.window) {
                         let workspace = event.data.get_long(0);
@@ -371,6 +503,27 @@ impl<A: PartialEq + Default> X11Backend<A> {
                         }
                     }
                 },
+                NetWMMoveresize => {
+                    if let Some(client_rc) = Self::client_by_window(wm, event.window) {
+                        if client_rc.borrow().is_fullscreen() {
+                            return;
+                        }
+
+                        match event.data.get_long(2) {
+                            NET_WM_MOVERESIZE_MOVE | NET_WM_MOVERESIZE_MOVE_KEYBOARD
+                                => self.mouse_action(wm, client_rc, CURSOR_MOVE, Self::mouse_action_move),
+                            NET_WM_MOVERESIZE_SIZE_KEYBOARD => {
+                                self.moveresize_direction = (1, 1);
+                                client_rc.borrow_mut().warp_pointer_to_corner();
+                                self.mouse_action(wm, client_rc, CURSOR_RESIZE, Self::mouse_action_resize_edge);
+                            },
+                            direction => if let Some(direction) = moveresize_direction(direction) {
+                                self.moveresize_direction = direction;
+                                self.mouse_action(wm, client_rc, CURSOR_RESIZE, Self::mouse_action_resize_edge);
+                            },
+                        }
+                    }
+                },
                 NetWMState => {
                     if let Some(client_rc) = Self::client_by_window(wm, event.window) {
                         if event.data.get_long(1) as u64 == NetWMStateFullscreen.to_xlib_atom(self.display)
@@ -401,6 +554,23 @@ impl<A: PartialEq + Default> X11Backend<A> {
                         wm.center_client(self, client_rc);
                     }
                 },
+                MarsAction => {
+                    // the action is too long for the message, so it is stored in a property of the sender's window
+                    let source = event.data.get_long(0) as xlib::Window;
+                    let action = source.x11_read_property_string(self.display, MarsAction);
+                    unsafe { xlib::XDeleteProperty(self.display, source, MarsAction.to_xlib_atom(self.display)); }
+                    if let Ok(action) = action {
+                        // only messages without a window address the active client
+                        if event.window == 0 {
+                            wm.execute_action(self, &action, None);
+                        } else if let Some(client_rc) = Self::client_by_window(wm, event.window) {
+                            wm.execute_action(self, &action, Some(client_rc));
+                        } else {
+                            eprintln!("Ignoring action '{}' for unknown window {}", action, event.window);
+                        }
+                    }
+                },
+                MarsIpc => wm.handle_requests(self),
                 _ => (),
             }
         }
@@ -442,36 +612,7 @@ impl<A: PartialEq + Default> X11Backend<A> {
                     inner.h()
                 };
 
-                let mut window_changed = false;
-                let (bw_north, bw_east, bw_south, bw_west) = client_rc.borrow().total_bw();
-
-                // issue move request if size is different
-                if x != inner.x() || y != inner.y() {
-                    let client = client_rc.borrow();
-                    // subtract border to size
-                    let x = x - bw_east as i32;
-                    let y = y - bw_north as i32;
-                    drop(client);
-                    // note that only moving might not generate a real ConfigureNotify
-                    // therefore we ignore the result of a move_request
-                    // window_changed |= wm.move_request(self, client_rc, x, y);
-                    wm.move_request(self, client_rc.clone(), x, y);
-                }
-
-                // issue resize request if size is different
-                if width != inner.w() || height != inner.h() {
-                    let client = client_rc.borrow();
-                    // add border to size
-                    let width = width + bw_east + bw_west;
-                    let height = height + bw_north + bw_south;
-                    drop(client);
-                    window_changed |= wm.resize_request(self, client_rc.clone(), width, height);
-                }
-
-                // send synthetic ConfigureNotify if the dimensions were not changed
-                if !window_changed {
-                    self.send_configure_notify(client_rc);
-                }
+                self.configure_client(wm, client_rc, x, y, width, height);
             }
         } else {
             let mut wc = xlib::XWindowChanges {
@@ -523,32 +664,47 @@ impl<A: PartialEq + Default> X11Backend<A> {
         } else if let Some(client_rc) = Self::client_by_window(wm, event.window) {
             Some(client_rc)
         } else {
-            unsafe {
-                xlib::XSetInputFocus(self.display, event.window, xlib::RevertToPointerRoot, xlib::CurrentTime);
+            if wm.focus_follows_pointer() {
+                unsafe {
+                    xlib::XSetInputFocus(self.display, event.window, xlib::RevertToPointerRoot, xlib::CurrentTime);
+                }
             }
             None
         };
 
-        wm.focus_client(self, client_option.clone());
+        if wm.focus_follows_pointer() {
+            wm.focus_client(self, client_option.clone());
+        }
     }
 
     fn on_expose_event(&mut self, wm: &mut (impl WindowManager<Self, A> + ?Sized), event: xlib::XExposeEvent) {
         if let Some(client_rc) = Self::client_by_frame(wm, event.window) {
             client_rc.borrow_mut().update_title();
+            client_rc.borrow_mut().redraw_title_buttons();
         }
     }
 
-    fn on_key_press(&mut self, wm: &mut (impl WindowManager<Self, A> + ?Sized), event: xlib::XKeyEvent) {
+    fn on_key_event(&mut self, wm: &mut (impl WindowManager<Self, A> + ?Sized), event: xlib::XKeyEvent) {
         //print_event!(wm, event);
 
         let keysym = unsafe {
             xlib::XKeycodeToKeysym(self.display, event.keycode.try_into().unwrap(), 0)
         };
 
+        // only pressing a modifier does not complete a key binding
+        if self.keyboard_grabbed && is_modifier_key(keysym) {
+            if event.type_ == xlib::KeyRelease {
+                let modifiers = sanitize_modifiers(event.state & !modifier_mask(self.display, event.keycode));
+                wm.handle_modifier_release(self, modifiers);
+            }
+            return;
+        }
+
         let modifiers = sanitize_modifiers(event.state);
         let client_opt = Self::client_by_frame(wm, event.window);
-        let key: u32 = keysym.try_into().unwrap();
-        wm.handle_key(self, modifiers, key, client_opt)
+        let keysym: u32 = keysym.try_into().unwrap();
+        let release = event.type_ == xlib::KeyRelease;
+        wm.handle_key(self, modifiers, event.keycode, keysym, release, client_opt)
     }
 
     fn on_leave_notify(&mut self, _wm: &mut (impl WindowManager<Self, A> + ?Sized), _event: xlib::XCrossingEvent) {
@@ -607,10 +763,16 @@ impl<A: PartialEq + Default> X11Backend<A> {
     }
 
     pub fn on_property_notify(&mut self, wm: &mut (impl WindowManager<Self, A> + ?Sized), event: xlib::XPropertyEvent) {
-        if let Some(client_rc) = Self::client_by_window(wm, event.window) {
+        if event.window == self.root && event.atom == xlib::XA_RESOURCE_MANAGER {
+            wm.resources_changed(self);
+        } else if let Some(client_rc) = Self::client_by_window(wm, event.window) {
             if let Some(atom) = X11Atom::from_xlib_atom(self.display, event.atom) {
                 match atom {
-                    WMName => client_rc.borrow_mut().update_title(),
+                    NetWMWindowOpacity => client_rc.borrow_mut().update_requested_opacity(),
+                    WMName | NetWMName => {
+                        client_rc.borrow_mut().update_title();
+                        wm.title_changed(self, client_rc);
+                    },
                     _ => (),
                 }
             }
@@ -659,7 +821,8 @@ impl<A: PartialEq + Default> X11Backend<A> {
 
 
     fn client_by_frame(wm: &(impl WindowManager<Self, A> + ?Sized), frame: u64) -> Option<Rc<RefCell<X11Client<A>>>> {
-        return wm.clients().find(|c| c.borrow().frame() == frame || c.borrow().title_window() == Some(frame)).cloned();
+        return wm.clients().find(|c| c.borrow().frame() == frame || c.borrow().title_window() == Some(frame)
+                                 || c.borrow().title_button_index(frame).is_some()).cloned();
     }
 
     fn client_by_window(wm: &(impl WindowManager<Self, A> + ?Sized), window: u64) -> Option<Rc<RefCell<X11Client<A>>>> {
@@ -682,6 +845,10 @@ impl XRandrInfo {
 impl<A: PartialEq + Default> Backend<A> for X11Backend<A> {
     type Client = X11Client<A>;
 
+    fn color_by_name(&self, name: &str) -> Option<u64> {
+        parse_color_name(self.display, name)
+    }
+
     fn export_active_window(&self, client_option: &Option<Rc<RefCell<Self::Client>>>) {
         let window = match client_option {
             Some(client_rc) => client_rc.borrow().window(),
@@ -702,6 +869,10 @@ impl<A: PartialEq + Default> Backend<A> for X11Backend<A> {
 
     }
 
+    fn export_binding_mode(&self, mode: &str) {
+        self.root.x11_set_text_list_property(self.display, MarsBindingMode, &[mode.to_owned()]);
+    }
+
     fn export_current_workspace(&self, workspace_idx: u32) {
         let idx: u64 = workspace_idx.into();
         let data = &[idx];
@@ -738,6 +909,28 @@ impl<A: PartialEq + Default> Backend<A> for X11Backend<A> {
         self.monitors.clone()
     }
 
+    fn bind_key(&mut self, modifiers: u32, key: KeyIdentifier) {
+        // other clients might have grabbed the key already
+        unsafe {
+            xlib::XSync(self.display, xlib::False);
+            xlib::XSetErrorHandler(Some(on_error_dummy));
+        }
+        grab_key(self.display, self.root, modifiers, key);
+        unsafe {
+            xlib::XSync(self.display, xlib::False);
+            xlib::XSetErrorHandler(Some(on_error));
+        }
+    }
+
+    fn grab_keyboard(&mut self) -> bool {
+        let result = unsafe {
+            xlib::XGrabKeyboard(self.display, self.root, xlib::False, xlib::GrabModeAsync, xlib::GrabModeAsync,
+                                xlib::CurrentTime)
+        };
+        self.keyboard_grabbed = result == xlib::GrabSuccess;
+        self.keyboard_grabbed
+    }
+
     fn handle_existing_windows(&mut self, wm: &mut WM<A>) {
         unsafe {
             xlib::XGrabServer(self.display);
@@ -866,6 +1059,21 @@ impl<A: PartialEq + Default> Backend<A> for X11Backend<A> {
         None
     }
 
+    fn modifier_state(&self) -> u32 {
+        unsafe {
+            let mut di: i32 = 0;
+            let mut mask: u32 = 0;
+            let mut dummy: xlib::Window = 0;
+
+            if xlib::XQueryPointer(self.display, self.root, &mut dummy, &mut dummy, &mut di, &mut di,
+                                   &mut di, &mut di, &mut mask) == xlib::True {
+                sanitize_modifiers(mask)
+            } else {
+                0
+            }
+        }
+    }
+
     fn pointer_pos(&self) -> (i32, i32) {
         unsafe {
             let mut x: i32 = 0;
@@ -900,6 +1108,13 @@ impl<A: PartialEq + Default> Backend<A> for X11Backend<A> {
         }
     }
 
+    fn ungrab_keyboard(&mut self) {
+        unsafe {
+            xlib::XUngrabKeyboard(self.display, xlib::CurrentTime);
+        }
+        self.keyboard_grabbed = false;
+    }
+
     fn warp_pointer(&self, x: i32, y: i32) {
         unsafe {
             // this might fail (best effort)
@@ -918,6 +1133,21 @@ impl<A: PartialEq + Default> Backend<A> for X11Backend<A> {
     }
 }
 
+/// Map an edge of _NET_WM_MOVERESIZE to the direction in which it changes the window size
+fn moveresize_direction(direction: i64) -> Option<(i32, i32)> {
+    match direction {
+        0 => Some((-1, -1)),  // top left
+        1 => Some((0, -1)),   // top
+        2 => Some((1, -1)),   // top right
+        3 => Some((1, 0)),    // right
+        4 => Some((1, 1)),    // bottom right
+        5 => Some((0, 1)),    // bottom
+        6 => Some((-1, 1)),   // bottom left
+        7 => Some((-1, 0)),   // left
+        _ => None,
+    }
+}
+
 #[allow(dead_code)]
 fn event_type<T>(_: &T) -> &str {
     return std::any::type_name::<T>();
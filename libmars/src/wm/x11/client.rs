@@ -9,6 +9,7 @@ use crate::common::x11::*;
 use crate::common::x11::atoms::*;
 use crate::common::x11::atoms::X11Atom::*;
 use crate::common::x11::window::*;
+use crate::draw::TextAlignment;
 use crate::draw::x11::widget::*;
 use crate::wm::*;
 use crate::wm::x11::*;
@@ -21,7 +22,10 @@ pub struct X11Client<A: PartialEq> {
     window: u64,
     frame: u64,
     title_widget: Option<X11TextWidget>,
+    title_buttons: Vec<X11TextWidget>,
+    title_format: String,
     attributes: A,
+    window_buttons: Vec<(u32, u32)>, // modifiers and buttons grabbed on the client window
 
     orig_pos: (i32, i32), // position prior to reparenting
     x: i32, y: i32, // x, y position
@@ -33,9 +37,12 @@ pub struct X11Client<A: PartialEq> {
     dont_decorate: bool,
     fullscreen: bool,
     is_dialog: bool,
+    urgent: bool,
     visible: bool,
 
     frame_color: u64,
+    opacity: f32,
+    requested_opacity: f32,
     saved_decorations: Option<(u32, u32, (u32, u32, u32, u32))>,
     saved_dimensions: Option<Dimensions>,
 }
@@ -67,6 +74,12 @@ impl<A: Default + PartialEq> X11Client<A> {
             xlib::XSelectInput(display, window, xlib::EnterWindowMask | xlib::LeaveWindowMask | xlib::PropertyChangeMask);
         }
 
+        // copy opacity to frame so compositors pick it up
+        let requested_opacity = read_opacity(display, window);
+        if let Ok(data) = window.x11_read_property_long(display, NetWMWindowOpacity, xlib::XA_CARDINAL) {
+            frame.x11_replace_property_long(display, NetWMWindowOpacity, xlib::XA_CARDINAL, &data);
+        }
+
         let name = match window.x11_class_hint(display) {
             Ok((name, class)) => format!("{}::{} [0x{:x}]", name, class, window),
             Err(msg) => {
@@ -79,7 +92,10 @@ impl<A: Default + PartialEq> X11Client<A> {
             name,
             display, root, window, frame,
             title_widget: None,
+            title_buttons: Vec::new(),
+            title_format: String::new(),
             attributes: A::default(),
+            window_buttons: Vec::new(),
 
             orig_pos: (x, y),
             x, y, w, h,
@@ -91,9 +107,12 @@ impl<A: Default + PartialEq> X11Client<A> {
             dont_decorate: false,
             fullscreen: false,
             is_dialog,
+            urgent: false,
             visible: false,
 
             frame_color: 0x000000,
+            opacity: 1.0,
+            requested_opacity,
             saved_decorations: None,
             saved_dimensions: None,
         } )
@@ -224,8 +243,63 @@ impl<A: PartialEq> X11Client<A> {
         }
     }
 
+    /// Export the current decoration size as _NET_FRAME_EXTENTS
+    fn update_frame_extents(&self) {
+        let (north, east, south, west) = self.total_bw();
+        let data = [west.into(), east.into(), north.into(), south.into()];
+        self.window.x11_replace_property_long(self.display, NetFrameExtents, xlib::XA_CARDINAL, &data);
+    }
+
+    fn grab_window_button(&self, modifiers: u32, button: u32) {
+        let mask: u32 = (xlib::ButtonPressMask | xlib::ButtonReleaseMask | xlib::ButtonMotionMask)
+            .try_into().unwrap();
+
+        for modifiers in alternative_modifiers(modifiers) {
+            unsafe {
+                xlib::XGrabButton(self.display, button, modifiers, self.window, xlib::False, mask,
+                                  xlib::GrabModeAsync, xlib::GrabModeAsync, self.window, 0);
+            }
+        }
+    }
+
+    pub fn update_requested_opacity(&mut self) {
+        self.requested_opacity = read_opacity(self.display, self.window);
+        self.set_opacity(self.opacity);
+    }
+
+    /// Place title buttons on the right and let the title fill the remaining width
+    fn arrange_title(&mut self) {
+        let mut x = (self.w - 2 * self.obw) as i32;
+        for button in self.title_buttons.iter_mut().rev() {
+            x -= button.size().0 as i32;
+            button.move_to(x, 0);
+        }
+
+        if let Some(title_widget) = &mut self.title_widget {
+            let width = cmp::max(x, 1) as u32;
+            title_widget.set_min_size((width, MIN_SIZE.1));
+            title_widget.set_max_size((width, MAX_SIZE.1));
+            title_widget.redraw();
+        }
+    }
+
+    fn formatted_title(&self) -> String {
+        self.title_format.replace("{class}", &self.application())
+            .replace("{title}", &self.title())
+    }
+
+    pub fn redraw_title_buttons(&mut self) {
+        for button in &mut self.title_buttons {
+            button.redraw();
+        }
+    }
+
+    pub fn title_button_index(&self, window: u64) -> Option<u32> {
+        self.title_buttons.iter().position(|b| b.wid() == window).map(|i| i as u32)
+    }
+
     pub fn update_title(&mut self) {
-        let title = self.title();
+        let title = self.formatted_title();
         if let Some(title_widget) = &mut self.title_widget {
             title_widget.set_label(title);
         }
@@ -249,16 +323,41 @@ impl<A: PartialEq> X11Client<A> {
 }
 
 impl<A: PartialEq> Client<A> for X11Client<A> {
-    fn add_title(&mut self, font: &str, hpad: u32, vpad: u32, color: u64) -> Result<()> {
-        let title = self.title();
+    fn add_title(&mut self, font: &str, hpad: u32, vpad: u32, color: u64, alignment: TextAlignment,
+                 format: &str) -> Result<()> {
+        self.title_format = format.to_owned();
+        let title = self.formatted_title();
         let mut widget = X11TextWidget::new(self.display, self.frame, 0, 0, hpad, vpad,
                                         title.clone(), font, color, self.frame_color)?;
         unsafe {
             xlib::XLowerWindow(self.display, widget.wid());
+            widget.set_alignment(alignment);
             widget.set_label(title);
         }
 
         self.title_widget = Some(widget);
+        self.arrange_title();
+        Ok(())
+    }
+
+    fn add_title_buttons(&mut self, font: &str, hpad: u32, vpad: u32, color: u64, labels: &[String]) -> Result<()> {
+        for label in labels {
+            let widget = X11TextWidget::new(self.display, self.frame, 0, 0, hpad, vpad,
+                                            label.clone(), font, color, self.frame_color)?;
+            unsafe {
+                xlib::XLowerWindow(self.display, widget.wid());
+            }
+            self.title_buttons.push(widget);
+        }
+
+        // keep the title below the buttons
+        if let Some(title_widget) = &self.title_widget {
+            unsafe {
+                xlib::XLowerWindow(self.display, title_widget.wid());
+            }
+        }
+
+        self.arrange_title();
         Ok(())
     }
 
@@ -278,32 +377,20 @@ impl<A: PartialEq> Client<A> for X11Client<A> {
     }
 
     fn bind_button(&mut self, modifiers: u32, button: u32, target: ButtonTarget) {
-        let mask: u32 = (xlib::ButtonPressMask | xlib::ButtonReleaseMask | xlib::ButtonMotionMask)
-            .try_into().unwrap();
-
-        let window = match target {
-            ButtonTarget::Window => self.window,
+        match target {
+            ButtonTarget::Window => {
+                self.window_buttons.push((modifiers, button));
+                self.grab_window_button(modifiers, button);
+            },
             // ButtonTarget::Frame => self.frame,
-            ButtonTarget::Frame => return,  // already grabbed as we own the window
+            ButtonTarget::Frame => (),  // already grabbed as we own the window
+            ButtonTarget::Title | ButtonTarget::TitleButton(_) => (),  // created by us as well
             ButtonTarget::Root => panic!("You can't bind actions to the root window through a client window"),
-        };
-
-        for modifiers in alternative_modifiers(modifiers) {
-            unsafe {
-                xlib::XGrabButton(self.display, button, modifiers, window, xlib::False, mask,
-                                  xlib::GrabModeAsync, xlib::GrabModeAsync, window, 0);
-            }
         }
     }
 
-    fn bind_key(&mut self, modifiers: u32, key: u32) {
-        unsafe {
-            let keycode = xlib::XKeysymToKeycode(self.display, key.into());
-            for modifiers in alternative_modifiers(modifiers) {
-                xlib::XGrabKey(self.display, keycode.into(), modifiers, self.frame, xlib::False,
-                                xlib::GrabModeAsync, xlib::GrabModeAsync);
-            }
-        }
+    fn bind_key(&mut self, modifiers: u32, key: KeyIdentifier) {
+        grab_key(self.display, self.frame, modifiers, key);
     }
 
     fn center_on_screen(&mut self, dimensions: Dimensions) {
@@ -321,6 +408,20 @@ impl<A: PartialEq> Client<A> for X11Client<A> {
         self.dont_decorate
     }
 
+    fn export_allowed_actions(&self, actions: &[ClientAction]) {
+        let data: Vec<u64> = actions.iter()
+            .map(|a| match a {
+                ClientAction::ChangeWorkspace => NetWMActionChangeDesktop,
+                ClientAction::Close => NetWMActionClose,
+                ClientAction::Fullscreen => NetWMActionFullscreen,
+                ClientAction::Move => NetWMActionMove,
+                ClientAction::Resize => NetWMActionResize,
+            })
+            .map(|a| a.to_xlib_atom(self.display))
+            .collect();
+        self.window.x11_replace_property_long(self.display, NetWMAllowedActions, xlib::XA_ATOM, &data);
+    }
+
     fn export_pinned(&self, state: bool, workspace_idx: Option<u32>) {
         let idx: u64 = if state { 0xffffffff } else { workspace_idx.expect("Need workspace index to unpin window").into() };
         let data = &[idx];
@@ -370,6 +471,10 @@ impl<A: PartialEq> Client<A> for X11Client<A> {
         self.visible = false;
     }
 
+    fn id(&self) -> u64 {
+        self.window
+    }
+
     fn inner_bw(&self) -> u32 {
         self.ibw
     }
@@ -398,6 +503,10 @@ impl<A: PartialEq> Client<A> for X11Client<A> {
         self.fullscreen
     }
 
+    fn is_urgent(&self) -> bool {
+        self.urgent
+    }
+
     fn is_visible(&self) -> bool {
         self.visible
     }
@@ -425,6 +534,7 @@ impl<A: PartialEq> Client<A> for X11Client<A> {
                                     inner_dimensions.x(), inner_dimensions.y(),
                                     inner_dimensions.w(), inner_dimensions.h());
         }
+        self.arrange_title();
     }
 
     fn name(&self) -> &str {
@@ -445,6 +555,22 @@ impl<A: PartialEq> Client<A> for X11Client<A> {
         self.move_resize(dimensions.x(), dimensions.y(), dimensions.w(), dimensions.h())
     }
 
+    fn set_focus_on_click(&mut self, state: bool) {
+        unsafe {
+            xlib::XUngrabButton(self.display, xlib::AnyButton as u32, xlib::AnyModifier, self.window);
+            if state {
+                // the pointer is frozen until the click is replayed to the client
+                xlib::XGrabButton(self.display, xlib::AnyButton as u32, xlib::AnyModifier, self.window, xlib::False,
+                                  xlib::ButtonPressMask as u32, xlib::GrabModeSync, xlib::GrabModeAsync, XLIB_NONE, XLIB_NONE);
+            }
+        }
+
+        // restore grabs for bindings, as they were released as well
+        for (modifiers, button) in &self.window_buttons {
+            self.grab_window_button(*modifiers, *button);
+        }
+    }
+
     fn set_frame_color(&mut self, color: u64) {
         unsafe {
             xlib::XSetWindowBackground(self.display, self.frame, color);
@@ -456,6 +582,9 @@ impl<A: PartialEq> Client<A> for X11Client<A> {
         if let Some(title_widget) = &mut self.title_widget {
             let _ignore_result = title_widget.set_background(color);
         }
+        for button in &mut self.title_buttons {
+            let _ignore_result = button.set_background(color);
+        }
     }
 
     fn set_frame_width(&mut self, width: (u32, u32, u32, u32)) {
@@ -465,6 +594,7 @@ impl<A: PartialEq> Client<A> for X11Client<A> {
         let diff_west = (width.3 as i32) - (self.fw.3 as i32);
         self.fw = width;
         self.move_resize(self.x - diff_west, self.y - diff_north, (self.w as i32 + diff_east + diff_west) as u32, (self.h as i32 + diff_north + diff_south) as u32);
+        self.update_frame_extents();
     }
 
     fn set_fullscreen(&mut self, monitor_conf: &MonitorConfig) {
@@ -488,6 +618,7 @@ impl<A: PartialEq> Client<A> for X11Client<A> {
             xlib::XSetWindowBorderWidth(self.display, self.window, self.ibw);
         }
         self.move_resize(self.x - diff, self.y - diff, (self.w as i32 + 2 * diff) as u32, (self.h as i32 + 2 * diff) as u32);
+        self.update_frame_extents();
     }
 
     fn set_inner_color(&mut self, color: u64) {
@@ -496,6 +627,20 @@ impl<A: PartialEq> Client<A> for X11Client<A> {
         }
     }
 
+    fn set_opacity(&mut self, opacity: f32) {
+        self.opacity = opacity.clamp(0.0, 1.0);
+        let effective_opacity = self.opacity * self.requested_opacity;
+        if effective_opacity >= 1.0 {
+            // leave opaque windows to the compositor's own rules
+            unsafe {
+                xlib::XDeleteProperty(self.display, self.frame, NetWMWindowOpacity.to_xlib_atom(self.display));
+            }
+        } else {
+            let data = [(effective_opacity as f64 * u32::MAX as f64) as u64];
+            self.frame.x11_replace_property_long(self.display, NetWMWindowOpacity, xlib::XA_CARDINAL, &data);
+        }
+    }
+
     fn set_outer_bw(&mut self, bw: u32) {
         let diff = (bw as i32) - (self.obw as i32);
         self.obw = bw;
@@ -503,6 +648,7 @@ impl<A: PartialEq> Client<A> for X11Client<A> {
             xlib::XSetWindowBorderWidth(self.display, self.frame, self.obw);
         }
         self.move_resize(self.x - diff, self.y - diff, (self.w as i32 + 2 * diff) as u32, (self.h as i32 + 2 * diff) as u32);
+        self.update_frame_extents();
     }
 
     fn set_outer_color(&mut self, color: u64) {
@@ -515,6 +661,18 @@ impl<A: PartialEq> Client<A> for X11Client<A> {
         if let Some(title_widget) = &mut self.title_widget {
             let _ignore_result = title_widget.set_foreground(color);
         }
+        for button in &mut self.title_buttons {
+            let _ignore_result = button.set_foreground(color);
+        }
+    }
+
+    fn set_urgent(&mut self, state: bool) {
+        if state {
+            self.x11_net_wm_state_add(self.display, NetWMStateDemandsAttention);
+        } else {
+            self.x11_net_wm_state_remove(self.display, NetWMStateDemandsAttention);
+        }
+        self.urgent = state;
     }
 
     fn show(&mut self) {
@@ -745,3 +903,10 @@ impl<A: PartialEq> X11Window for X11Client<A> {
         self.window.x11_wm_normal_hints(display)
     }
 }
+
+fn read_opacity(display: *mut xlib::Display, window: xlib::Window) -> f32 {
+    match window.x11_read_property_long(display, NetWMWindowOpacity, xlib::XA_CARDINAL) {
+        Ok(data) if !data.is_empty() => (data[0] as u32) as f32 / u32::MAX as f32,
+        _ => 1.0,
+    }
+}
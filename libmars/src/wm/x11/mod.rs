@@ -1,10 +1,26 @@
 use x11::xlib;
 use std::ffi::*;
 
+use crate::common::x11::alternative_modifiers;
+use crate::wm::KeyIdentifier;
+
 pub mod backend;
 mod client;
 mod unmanaged;
 
+fn grab_key(display: *mut xlib::Display, window: xlib::Window, modifiers: u32, key: KeyIdentifier) {
+    unsafe {
+        let keycode: c_int = match key {
+            KeyIdentifier::Keysym(keysym) => xlib::XKeysymToKeycode(display, keysym.into()).into(),
+            KeyIdentifier::Keycode(keycode) => keycode as c_int,
+        };
+        for modifiers in alternative_modifiers(modifiers) {
+            xlib::XGrabKey(display, keycode, modifiers, window, xlib::False,
+                           xlib::GrabModeAsync, xlib::GrabModeAsync);
+        }
+    }
+}
+
 extern "C" fn on_wm_detected(_: *mut xlib::Display, _: *mut xlib::XErrorEvent) -> c_int {
     panic!("Another Window Manager seems to be running already");
 }
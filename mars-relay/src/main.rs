@@ -3,13 +3,19 @@ extern crate x11;
 use clap::Parser;
 use libmars::common::error::MarsError;
 use libmars::control::*;
+use libmars::control::ipc::IpcController;
 use libmars::control::x11::X11Controller;
+use std::path::PathBuf;
 use x11::xlib;
 
 use crate::menu::*;
+use crate::output::*;
+use crate::selector::*;
 
 
 mod menu;
+mod output;
+mod selector;
 
 
 /// Sending commands to marswm and other window managers
@@ -21,9 +27,8 @@ struct Args {
     #[clap(subcommand)]
     command: Command,
 
-    /// Window to execute command on (defaults to currently active window)
-    #[clap(short, long)]
-    window: Option<xlib::Window>,
+    #[clap(flatten)]
+    selector: Selector,
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, clap::Args)]
@@ -32,6 +37,50 @@ pub struct Workspace { index: u32 }
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, clap::Args)]
 pub struct Status { text: String }
 
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, clap::Args)]
+pub struct Action {
+    /// Action in YAML notation (e.g. 'set-layout: monocle')
+    action: String,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, clap::Args)]
+pub struct Format {
+    /// Print output as JSON
+    #[clap(long)]
+    json: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, clap::Args)]
+pub struct LayoutQuery {
+    /// Workspace to query (defaults to current workspace)
+    workspace: Option<u32>,
+
+    #[clap(flatten)]
+    format: Format,
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, clap::Args)]
+pub struct MenuOpts {
+    /// Menu program with arguments (e.g. 'rofi -dmenu', overrides the configuration)
+    #[clap(long)]
+    program: Option<String>,
+
+    /// Menu configuration (defaults to ~/.config/marswm/menu.yaml)
+    #[clap(long)]
+    config: Option<PathBuf>,
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, clap::Args)]
+pub struct RunOrRaise {
+    /// Class of the windows to raise (case insensitive)
+    #[clap(long)]
+    class: String,
+
+    /// Command to run if there is no matching window
+    #[clap(last = true, required = true)]
+    command: Vec<String>,
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, clap::Args)]
 pub struct ModifierShim {
     #[clap(value_enum)]
@@ -40,6 +89,9 @@ pub struct ModifierShim {
 
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, clap::Subcommand)]
 pub enum Command {
+    /// Execute any action of the window manager (marswm only)
+    Action(Action),
+
     /// Activate window
     Activate,
 
@@ -52,18 +104,36 @@ pub enum Command {
     /// Fullscreen setting of a window
     Fullscreen(ModifierShim),
 
+    /// Print layout of a workspace (marswm only)
+    GetLayout(LayoutQuery),
+
+    /// List all managed windows
+    ListClients(Format),
+
+    /// List all monitors
+    ListMonitors(Format),
+
     /// Show quick menu
-    Menu,
+    Menu(MenuOpts),
 
     /// Pin window so it is visible on all workspaces
     Pinned(ModifierShim),
 
+    /// Activate the next window of an application or run a command if there is none
+    RunOrRaise(RunOrRaise),
+
     /// Send window to workspace
     SendToWorkspace(Workspace),
 
     /// Set status string
     SetStatus(Status),
 
+    /// Print events of the window manager as JSON (one per line)
+    Subscribe,
+
+    /// Select a window from a menu of all windows and activate it
+    SwitchWindow(MenuOpts),
+
     /// Switch current workspace
     SwitchWorkspace(Workspace),
 
@@ -97,7 +167,7 @@ impl From<Modifier> for SettingMode {
     }
 }
 
-fn handle_window_setting<C, G, S>(getter: G, setter: S, controller: &C, window: xlib::Window, opt: ModifierShim)
+fn handle_window_setting<C, G, S>(getter: G, setter: S, controller: &C, windows: Vec<xlib::Window>, opt: ModifierShim)
     -> libmars::common::error::Result<()>
 where
     C: WMController<xlib::Window>,
@@ -105,10 +175,18 @@ where
     G: Fn(&C, xlib::Window) -> libmars::common::error::Result<bool>,
 {
     if let Some(modifier) = opt.modifier {
-        setter(controller, window, SettingMode::from(modifier))
+        windows.into_iter().try_for_each(|window| setter(controller, window, SettingMode::from(modifier)))
     } else {
-        let state = getter(controller, window)?;
-        println!("{}", state);
+        let mut state = true;
+        for window in &windows {
+            let window_state = getter(controller, *window)?;
+            if windows.len() > 1 {
+                println!("{} {}", window, window_state);
+            } else {
+                println!("{}", window_state);
+            }
+            state &= window_state;
+        }
         if state {
             std::process::exit(0);
         } else {
@@ -119,38 +197,94 @@ where
 
 
 impl Command {
-    fn execute<C: WMController<xlib::Window>>(&self, controller: &C, window_result: Result<xlib::Window, String>,
-                                              args: Args) -> Result<(), String> {
+    fn execute<C: WMController<xlib::Window>>(&self, controller: &C, selector: &Selector) -> Result<(), String> {
 
-        if *self == Command::Menu {
-            Self::menu(controller, window_result, args)
+        if let Command::Menu(opts) = self {
+            Self::menu(controller, opts, selector)
         } else {
+            let windows = || selector.windows(controller);
             let result = match self {
-                Command::Activate => controller.activate_window(window_result?),
-                Command::Center => controller.center_window(window_result?),
-                Command::Close => controller.close_window(window_result?),
+                Command::Action(action) if selector.is_active() => controller.execute_action(None, &action.action),
+                Command::Action(action) => windows()?.into_iter()
+                    .try_for_each(|window| controller.execute_action(Some(window), &action.action)),
+                Command::Activate => windows()?.into_iter().try_for_each(|window| controller.activate_window(window)),
+                Command::Center => windows()?.into_iter().try_for_each(|window| controller.center_window(window)),
+                Command::Close => windows()?.into_iter().try_for_each(|window| controller.close_window(window)),
                 Command::Fullscreen(mode) => handle_window_setting(C::window_is_fullscreen, C::fullscreen_window, controller,
-                                                                   window_result?, *mode),
-                Command::Pinned(mode) => handle_window_setting(C::window_is_pinned, C::pin_window, controller, window_result?, *mode),
-                Command::SendToWorkspace(ws) => controller.send_window_to_workspace(window_result?, ws.index),
+                                                                   windows()?, *mode),
+                Command::GetLayout(query) => return Self::get_layout(controller, *query),
+                Command::ListClients(format) => {
+                    let clients = controller.clients().map_err(|e| e.to_string())?;
+                    return if format.json { print_json(&clients) } else { print_clients(&clients); Ok(()) };
+                },
+                Command::ListMonitors(format) => {
+                    let monitors = controller.monitors().map_err(|e| e.to_string())?;
+                    return if format.json { print_json(&monitors) } else { print_monitors(&monitors); Ok(()) };
+                },
+                Command::Pinned(mode) => handle_window_setting(C::window_is_pinned, C::pin_window, controller, windows()?, *mode),
+                Command::RunOrRaise(opts) => return Self::run_or_raise(controller, opts),
+                Command::SendToWorkspace(ws) => windows()?.into_iter()
+                    .try_for_each(|window| controller.send_window_to_workspace(window, ws.index)),
                 Command::SetStatus(status) => controller.set_status(status.text.to_owned()),
+                Command::Subscribe => controller.subscribe(&mut |event| {
+                    if let Ok(line) = serde_json::to_string(&event) {
+                        println!("{}", line);
+                    }
+                }),
+                Command::SwitchWindow(opts) => match select_window(controller, opts)? {
+                    Some(window) => controller.activate_window(window),
+                    None => Ok(()),
+                },
                 Command::SwitchWorkspace(ws) => controller.switch_workspace(ws.index),
                 Command::SwitchWorkspaceNext => Self::switch_workspace_relative(controller, 1),
                 Command::SwitchWorkspacePrev => Self::switch_workspace_relative(controller, -1),
-                Command::Tiled(mode) => handle_window_setting(C::window_is_tiled, C::tile_window, controller, window_result?, *mode),
-                Command::Menu => panic!("unhandled command"),
+                Command::Tiled(mode) => handle_window_setting(C::window_is_tiled, C::tile_window, controller, windows()?, *mode),
+                Command::Menu(_) => panic!("unhandled command"),
             };
             result.map_err(|e| e.to_string())
         }
     }
 
-    fn menu(controller: &impl WMController<xlib::Window>, window_result: Result<xlib::Window, String>, args: Args)
-            -> Result<(), String> {
-        let command = match display_menu() {
-            Ok(cmd) => cmd,
-            Err(e) => { eprintln!("Error: {}", e); return Err("unable to display menu".to_owned()); },
+    fn get_layout(controller: &impl WMController<xlib::Window>, query: LayoutQuery) -> Result<(), String> {
+        let workspace = match query.workspace {
+            Some(workspace) => workspace,
+            None => controller.current_workspace().map_err(|e| e.to_string())?,
+        };
+        let layout = controller.layout(workspace).map_err(|e| e.to_string())?;
+        if query.format.json {
+            print_json(&LayoutInfo { workspace, layout })
+        } else {
+            println!("{}", layout);
+            Ok(())
+        }
+    }
+
+    fn menu(controller: &impl WMController<xlib::Window>, opts: &MenuOpts, selector: &Selector) -> Result<(), String> {
+        match display_menu(controller, opts)? {
+            Some(command) => command.execute(controller, selector),
+            None => Ok(()),  // menu closed without selection
+        }
+    }
+
+    fn run_or_raise(controller: &impl WMController<xlib::Window>, opts: &RunOrRaise) -> Result<(), String> {
+        let clients = controller.clients().map_err(|e| e.to_string())?;
+        let matches: Vec<&ClientInfo> = clients.iter()
+            .filter(|c| c.class.eq_ignore_ascii_case(&opts.class))
+            .collect();
+        // cycle through the windows on repeated invocation
+        let next = match matches.iter().position(|c| c.active) {
+            Some(idx) => matches.get((idx + 1) % matches.len()),
+            None => matches.first(),
         };
-        command.execute(controller, window_result, args)
+
+        match next {
+            Some(client) => controller.activate_window(client.window).map_err(|e| e.to_string()),
+            None => std::process::Command::new(&opts.command[0])
+                .args(&opts.command[1..])
+                .spawn()
+                .map(|_| ())
+                .map_err(|e| format!("Unable to run `{}`: {}", opts.command[0], e)),
+        }
     }
 
     fn switch_workspace_relative(controller: &impl WMController<xlib::Window>, inc: i32) -> Result<(), MarsError> {
@@ -161,9 +295,22 @@ impl Command {
     }
 }
 
+fn run<C: WMController<xlib::Window>>(controller: &C, args: Args) {
+    if let Err(msg) = args.command.execute(controller, &args.selector) {
+        eprintln!("Error: {}", msg);
+        std::process::exit(1);
+    }
+}
+
 fn main() {
     let args = Args::parse();
 
+    // prefer the IPC socket of marswm and fall back to EWMH messages for other window managers
+    if let Some(controller) = IpcController::new() {
+        run(&controller, args);
+        return;
+    }
+
     let controller = match X11Controller::new() {
         Ok(ctrl) => ctrl,
         Err(e) => {
@@ -171,14 +318,5 @@ fn main() {
             std::process::exit(1);
         },
     };
-
-    let command = args.command.clone();
-    let window_result = match args.window {
-        Some(window) => Ok(window),
-        None => controller.get_active_window().map_err(|_| format!("Unable to get active window")),
-    };
-    if let Err(msg) = command.execute(&controller, window_result, args) {
-        eprintln!("Error: {}", msg);
-        std::process::exit(1);
-    }
+    run(&controller, args);
 }
@@ -1,28 +1,194 @@
+use clap::Parser;
+use libmars::utils::configuration::{find_config_file, read_file};
+use serde::{Deserialize, Serialize};
 use std::io::Write;
+use std::path::Path;
 use std::process;
 use std::thread;
 
 use crate::*;
 
-const MENU_PROGRAM: &str = "xmenu";
+const CONFIG_NAME: &str = "marswm";
+const CONFIG_FILE: &str = "menu.yaml";
+/// Separator of submenu and entry labels for menu programs without submenus
+const PATH_SEPARATOR: &str = " > ";
 
-fn menu_input() -> String {
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct MenuConfig {
+    /// menu program and its arguments
+    program: Vec<String>,
+
+    /// input format of the menu program (defaults to `xmenu` for xmenu and `dmenu` otherwise)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format: Option<MenuFormat>,
+
+    /// entries of the menu
+    entries: Vec<MenuEntry>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum MenuFormat {
+    /// flat list of entries, the selected line is returned (dmenu, rofi -dmenu, fzf, ...)
+    Dmenu,
+    /// tab-indented submenus with a separate output for each entry
+    Xmenu,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum MenuEntry {
+    /// Execute a command of mars-relay (e.g. `[send-to-workspace, "2"]`)
+    Command { label: String, command: Vec<String> },
+    /// Submenu with further entries
+    Submenu { label: String, entries: Vec<MenuEntry> },
+    /// Submenu with an entry for each workspace that executes the command with the workspace index appended
+    Workspaces { label: String, command: Vec<String> },
+    /// Submenu with an entry for each layout of the window manager that switches to it (marswm only)
+    Layouts { label: String },
+}
+
+/// Command line of a menu entry
+#[derive(Parser)]
+struct MenuCommand {
+    #[clap(subcommand)]
+    command: Command,
+}
+
+/// Entry of the flattened menu (without command for submenus)
+struct MenuItem {
+    depth: usize,
+    label: String,
+    path: String,
+    command: Option<Vec<String>>,
+}
+
+
+impl Default for MenuConfig {
+    fn default() -> Self {
+        MenuConfig {
+            program: vec!["xmenu".to_owned()],
+            format: None,
+            entries: vec![
+                command_entry("x Close", &["close"]),
+                command_entry("+ Center", &["center"]),
+                command_entry("% Tile", &["tiled", "toggle"]),
+                command_entry("* Fullscreen", &["fullscreen", "toggle"]),
+                command_entry("' Pin", &["pinned", "toggle"]),
+                MenuEntry::Workspaces { label: "> Send to Workspace".to_owned(), command: vec!["send-to-workspace".to_owned()] },
+                MenuEntry::Layouts { label: "# Layout".to_owned() },
+            ],
+        }
+    }
+}
+
+impl MenuConfig {
+    fn format(&self) -> MenuFormat {
+        let is_xmenu = self.program.first()
+            .and_then(|program| Path::new(program).file_name())
+            .is_some_and(|name| name == "xmenu");
+        match self.format {
+            Some(format) => format,
+            None if is_xmenu => MenuFormat::Xmenu,
+            None => MenuFormat::Dmenu,
+        }
+    }
+}
+
+
+fn command_entry(label: &str, command: &[&str]) -> MenuEntry {
+    MenuEntry::Command { label: label.to_owned(), command: command.iter().map(|s| s.to_string()).collect() }
+}
+
+/// Entries provided by the window manager
+struct MenuContext {
+    layouts: Vec<String>,
+    workspaces: Vec<String>,
+}
+
+fn flatten(entries: &[MenuEntry], context: &MenuContext, depth: usize, prefix: &str, items: &mut Vec<MenuItem>) {
+    for entry in entries {
+        let (label, command) = match entry {
+            MenuEntry::Command { label, command } => (label, Some(command.clone())),
+            MenuEntry::Submenu { label, .. } | MenuEntry::Workspaces { label, .. }
+                | MenuEntry::Layouts { label } => (label, None),
+        };
+        let path = format!("{}{}", prefix, label);
+        items.push(MenuItem { depth, label: label.clone(), path: path.clone(), command });
+
+        let prefix = format!("{}{}", path, PATH_SEPARATOR);
+        match entry {
+            MenuEntry::Command { .. } => (),
+            MenuEntry::Submenu { entries, .. } => flatten(entries, context, depth + 1, &prefix, items),
+            MenuEntry::Workspaces { command, .. } => for (i, name) in context.workspaces.iter().enumerate() {
+                let mut command = command.clone();
+                command.push(i.to_string());
+                items.push(MenuItem {
+                    depth: depth + 1,
+                    label: name.clone(),
+                    path: format!("{}{}", prefix, name),
+                    command: Some(command),
+                });
+            },
+            MenuEntry::Layouts { .. } => for layout in &context.layouts {
+                items.push(MenuItem {
+                    depth: depth + 1,
+                    label: layout.clone(),
+                    path: format!("{}{}", prefix, layout),
+                    command: Some(vec!["action".to_owned(), format!("set-layout: {}", layout)]),
+                });
+            },
+        }
+    }
+}
+
+/// Menu configuration with the program overridden by the command line
+fn menu_config(opts: &MenuOpts) -> Result<MenuConfig, String> {
+    let mut config = read_menu_config(opts.config.as_deref())?;
+    if let Some(program) = &opts.program {
+        config.program = program.split_whitespace().map(|s| s.to_owned()).collect();
+        config.format = None;
+    }
+    Ok(config)
+}
+
+fn menu_input(items: &[MenuItem], format: MenuFormat) -> String {
     let mut string = String::new();
-    string.push_str("x Close	close\n");
-    string.push_str("+ Center	center\n");
-    string.push_str("% Tile	tile\n");
-    string.push_str("* Fullscreen	fullscreen\n");
-    string.push_str("' Pin	pin\n");
+    for (i, item) in items.iter().enumerate() {
+        match format {
+            MenuFormat::Dmenu if item.command.is_some() => string.push_str(&format!("{}\n", item.path)),
+            MenuFormat::Dmenu => (),
+            MenuFormat::Xmenu if item.command.is_some() =>
+                string.push_str(&format!("{}{}\t{}\n", "\t".repeat(item.depth), item.label, i)),
+            MenuFormat::Xmenu => string.push_str(&format!("{}{}\n", "\t".repeat(item.depth), item.label)),
+        }
+    }
 
     string
 }
 
-pub fn display_menu() -> Result<Command, String> {
-    let mut child = process::Command::new(MENU_PROGRAM)
+fn read_menu_config(path: Option<&Path>) -> Result<MenuConfig, String> {
+    let path = match path {
+        Some(path) => Some(path.to_owned()),
+        None => find_config_file(CONFIG_NAME, CONFIG_FILE)?,
+    };
+    match path {
+        Some(path) => read_file(&path).map_err(|e| format!("Unable to read menu configuration: {}", e)),
+        None => Ok(MenuConfig::default()),
+    }
+}
+
+fn run_program(program: &[String], input: String) -> Result<String, String> {
+    let (name, args) = program.split_first()
+        .ok_or("no menu program configured")?;
+    let mut child = process::Command::new(name)
+        .args(args)
         .stdin(process::Stdio::piped())
         .stdout(process::Stdio::piped())
         .spawn()
-        .map_err(|_| format!("Please make sure `{}` is installed properly", MENU_PROGRAM))?;
+        .map_err(|_| format!("Please make sure `{}` is installed properly", name))?;
 
     let mut stdin = match child.stdin.take() {
         Some(stdin) => stdin,
@@ -30,22 +196,82 @@ pub fn display_menu() -> Result<Command, String> {
     };
     // might cause deadlock if the program doesn't clear the pipe without reading
     thread::spawn(move || {
-        let string = menu_input();
-        stdin.write_all(string.as_bytes()).unwrap();
+        let _ignored = stdin.write_all(input.as_bytes());
     });
 
-
     let output = child.wait_with_output()
         .map_err(|e| e.to_string())?;
-    let output = String::from_utf8_lossy(&output.stdout)
-        .to_string();
-
-    return match output.trim() {
-        "close" => Ok(Command::Close),
-        "center" => Ok(Command::Center),
-        "tile" => Ok(Command::Tiled(Modifier::Toggle.into())),
-        "fullscreen" => Ok(Command::Fullscreen(Modifier::Toggle.into())),
-        "pin" => Ok(Command::Pinned(Modifier::Toggle.into())),
-        _ => return Err("no match".to_string()),
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Show the menu and return the selected command
+///
+/// Returns `None` if the menu was closed without selecting an entry.
+pub fn display_menu(controller: &impl WMController<xlib::Window>, opts: &MenuOpts) -> Result<Option<Command>, String> {
+    let config = menu_config(opts)?;
+    let format = config.format();
+
+    let context = MenuContext {
+        layouts: controller.available_layouts().unwrap_or_default(),
+        workspaces: controller.workspaces().unwrap_or_default(),
+    };
+    let mut items = Vec::new();
+    flatten(&config.entries, &context, 0, "", &mut items);
+
+    let output = run_program(&config.program, menu_input(&items, format))?;
+    let selection = output.trim();
+    if selection.is_empty() {
+        return Ok(None);
+    }
+
+    let item = match format {
+        MenuFormat::Dmenu => items.iter().find(|item| item.command.is_some() && item.path == selection),
+        MenuFormat::Xmenu => selection.parse::<usize>().ok().and_then(|i| items.get(i)),
+    };
+    let command = item.and_then(|item| item.command.as_ref())
+        .ok_or_else(|| format!("no menu entry '{}'", selection))?;
+
+    let args = std::iter::once("menu").chain(command.iter().map(|s| s.as_str()));
+    MenuCommand::try_parse_from(args)
+        .map(|menu_command| Some(menu_command.command))
+        .map_err(|e| e.to_string())
+}
+
+/// Show a menu of all windows and return the selected one
+///
+/// Returns `None` if the menu was closed without selecting a window.
+pub fn select_window(controller: &impl WMController<xlib::Window>, opts: &MenuOpts)
+        -> Result<Option<xlib::Window>, String> {
+    let config = menu_config(opts)?;
+    let format = config.format();
+
+    let workspaces = controller.workspaces().unwrap_or_default();
+    let mut clients = controller.clients().map_err(|e| e.to_string())?;
+    clients.sort_by_key(|c| c.workspace);
+    let labels: Vec<String> = clients.iter().map(|c| {
+        let workspace = match c.workspace {
+            _ if c.pinned => "*".to_owned(),
+            Some(ws) => workspaces.get(ws as usize).cloned().unwrap_or_else(|| ws.to_string()),
+            None => "-".to_owned(),
+        };
+        format!("[{}] {} - {}", workspace, c.class, c.title).replace(['\t', '\n'], " ")
+    }).collect();
+
+    let input: String = labels.iter().enumerate().map(|(i, label)| match format {
+        MenuFormat::Dmenu => format!("{}\n", label),
+        MenuFormat::Xmenu => format!("{}\t{}\n", label, i),
+    }).collect();
+    let output = run_program(&config.program, input)?;
+    let selection = output.trim();
+    if selection.is_empty() {
+        return Ok(None);
+    }
+
+    let index = match format {
+        MenuFormat::Dmenu => labels.iter().position(|label| label.trim() == selection),
+        MenuFormat::Xmenu => selection.parse::<usize>().ok(),
     };
+    index.and_then(|i| clients.get(i))
+        .map(|c| Some(c.window))
+        .ok_or_else(|| format!("no window '{}'", selection))
 }
@@ -0,0 +1,70 @@
+use serde::Serialize;
+
+use crate::*;
+
+
+pub fn print_json<T: Serialize>(value: &T) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    println!("{}", json);
+    Ok(())
+}
+
+/// Print rows as table with aligned columns
+///
+/// The last column is not padded, so it may contain arbitrarily long text.
+pub fn print_table(header: &[&str], rows: &[Vec<String>]) {
+    let mut widths: Vec<usize> = header.iter().map(|h| h.chars().count()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let header: Vec<String> = header.iter().map(|h| h.to_string()).collect();
+    for row in std::iter::once(&header).chain(rows) {
+        let line: Vec<String> = row.iter().zip(&widths).map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect();
+        println!("{}", line.join("  ").trim_end());
+    }
+}
+
+pub fn print_clients(clients: &[ClientInfo]) {
+    let rows: Vec<Vec<String>> = clients.iter().map(|c| {
+        let workspace = match c.workspace {
+            _ if c.pinned => "*".to_owned(),
+            Some(ws) => ws.to_string(),
+            None => "-".to_owned(),
+        };
+        let state: Vec<&str> = [(c.active, "active"), (c.fullscreen, "fullscreen"), (c.pinned, "pinned"), (c.tiled, "tiled")]
+            .iter().filter(|(set, _)| *set).map(|(_, name)| *name).collect();
+        vec![
+            c.window.to_string(),
+            workspace,
+            option_to_string(c.monitor),
+            geometry(c.x, c.y, c.width, c.height),
+            if state.is_empty() { "-".to_owned() } else { state.join(",") },
+            c.class.clone(),
+            c.title.clone(),
+        ]
+    }).collect();
+    print_table(&["WINDOW", "WORKSPACE", "MONITOR", "GEOMETRY", "STATE", "CLASS", "TITLE"], &rows);
+}
+
+pub fn print_monitors(monitors: &[MonitorInfo]) {
+    let rows: Vec<Vec<String>> = monitors.iter().map(|m| vec![
+        m.index.to_string(),
+        m.name.clone(),
+        geometry(m.x, m.y, m.width, m.height),
+        option_to_string(m.workspace),
+    ]).collect();
+    print_table(&["INDEX", "NAME", "GEOMETRY", "WORKSPACE"], &rows);
+}
+
+/// Geometry in X11 notation (`WIDTHxHEIGHT+X+Y`)
+fn geometry(x: i32, y: i32, width: u32, height: u32) -> String {
+    format!("{}x{}{:+}{:+}", width, height, x, y)
+}
+
+fn option_to_string(value: Option<u32>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_else(|| "-".to_owned())
+}
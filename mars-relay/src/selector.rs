@@ -0,0 +1,76 @@
+use regex::Regex;
+
+use crate::*;
+
+
+/// Criteria to select the windows a command is executed on
+///
+/// All given criteria have to match.
+/// Without any criteria the command is executed on the active window.
+#[derive(Clone, Debug, Default, clap::Args)]
+pub struct Selector {
+    /// Window to execute command on (defaults to currently active window)
+    #[clap(short, long)]
+    window: Option<xlib::Window>,
+
+    /// Select windows by their class (case insensitive)
+    #[clap(long)]
+    class: Option<String>,
+
+    /// Select windows with a title matching a regular expression
+    #[clap(long)]
+    title_regex: Option<Regex>,
+
+    /// Select windows on a workspace
+    #[clap(long)]
+    workspace: Option<u32>,
+
+    /// Select a window by clicking on it (Escape cancels)
+    #[clap(long)]
+    pick: bool,
+}
+
+
+impl Selector {
+    /// Whether the command should be executed on the active window
+    pub fn is_active(&self) -> bool {
+        self.window.is_none() && !self.has_criteria()
+    }
+
+    pub fn windows(&self, controller: &impl WMController<xlib::Window>) -> Result<Vec<xlib::Window>, String> {
+        if !self.has_criteria() {
+            return match self.window {
+                Some(window) => Ok(vec![window]),
+                None => controller.get_active_window().map(|window| vec![window])
+                    .map_err(|_| "Unable to get active window".to_owned()),
+            };
+        }
+
+        let picked = if self.pick {
+            Some(controller.pick_window().map_err(|e| e.to_string())?)
+        } else {
+            None
+        };
+        let windows: Vec<xlib::Window> = controller.clients().map_err(|e| e.to_string())?.iter()
+            .filter(|client| picked.is_none_or(|window| window == client.window) && self.matches(client))
+            .map(|client| client.window)
+            .collect();
+
+        if windows.is_empty() {
+            Err("No matching window".to_owned())
+        } else {
+            Ok(windows)
+        }
+    }
+
+    fn has_criteria(&self) -> bool {
+        self.class.is_some() || self.title_regex.is_some() || self.workspace.is_some() || self.pick
+    }
+
+    fn matches(&self, client: &ClientInfo) -> bool {
+        self.window.is_none_or(|window| window == client.window)
+            && self.class.as_ref().is_none_or(|class| class.eq_ignore_ascii_case(&client.class))
+            && self.title_regex.as_ref().is_none_or(|regex| regex.is_match(&client.title))
+            && self.workspace.is_none_or(|workspace| client.workspace == Some(workspace))
+    }
+}
@@ -1,6 +1,9 @@
+use libmars::common::color::Color;
+use libmars::common::x11::parse_color_name;
 use libmars::draw::x11::widget::*;
 use libmars::utils::configuration::read_config_file;
 use libmars::utils::configuration::read_file;
+use libmars::utils::theme::load_theme;
 use serde::{Serialize, Deserialize};
 use std::path;
 use x11::xlib;
@@ -24,7 +27,7 @@ pub trait CreateWidget<W: Widget> {
 #[derive(Serialize,Deserialize,PartialEq,Debug,Clone)]
 #[serde(default)]
 pub struct BarStyle {
-    pub background: u64,
+    pub background: Color,
     pub expand_workspace_widgets: bool,
     pub height: u32,
     pub font: String,
@@ -36,17 +39,17 @@ pub struct BarStyle {
 #[derive(Serialize,Deserialize,PartialEq,Debug,Clone)]
 #[serde(default)]
 pub struct TextWidgetStyle {
-    pub foreground: u64,
-    pub background: u64,
+    pub foreground: Color,
+    pub background: Color,
     pub padding_horz: u32,
     pub padding_vert: u32,
 }
 
 #[derive(Serialize,Deserialize,PartialEq,Debug,Clone)]
 pub struct ContainerWidgetStyle {
-    pub foreground: u64,
-    pub inner_background: u64,
-    pub outer_background: u64,
+    pub foreground: Color,
+    pub inner_background: Color,
+    pub outer_background: Color,
     pub padding_horz: u32,
     pub padding_vert: u32,
     pub text_padding_horz: u32,
@@ -66,7 +69,7 @@ pub struct Configuration {
 impl Default for BarStyle {
     fn default() -> Self {
         BarStyle {
-            background: 0x262626,
+            background: Color::from(0x262626),
             expand_workspace_widgets: false,
             height: 27,
             font: DEFAULT_FONT.to_owned(),
@@ -77,11 +80,22 @@ impl Default for BarStyle {
     }
 }
 
+impl BarStyle {
+    /// Look up named colors (see [Color::resolve]) and return the ones that cannot be resolved
+    pub fn resolve_colors(&mut self, color_by_name: &dyn Fn(&str) -> Option<u64>) -> Vec<String> {
+        let mut problems: Vec<String> = self.background.resolve(color_by_name).err().into_iter().collect();
+        problems.extend(self.workspaces.resolve_colors(color_by_name));
+        problems.extend(self.title.resolve_colors(color_by_name));
+        problems.extend(self.status.resolve_colors(color_by_name));
+        problems
+    }
+}
+
 impl Default for TextWidgetStyle {
     fn default() -> Self {
         TextWidgetStyle {
-            foreground: 0xbcbcbc,
-            background: 0x262626,
+            foreground: Color::from(0xbcbcbc),
+            background: Color::from(0x262626),
             padding_horz: DEFAULT_TEXT_PADDING_HORZ,
             padding_vert: DEFAULT_TEXT_PADDING_VERT
         }
@@ -92,29 +106,35 @@ impl ContainerWidgetStyle {
     pub fn create_flow_layout_widget<W: Widget>(&self, display: *mut xlib::Display, parent: xlib::Window)
             -> Result<X11FlowLayoutWidget<W>, String> {
         X11FlowLayoutWidget::new(display, parent, 0, 0, self.padding_horz, self.padding_vert, self.spacing,
-                                     Vec::new(), self.outer_background)
+                                     Vec::new(), self.outer_background.pixel())
             .map_err(|e| e.to_string())
     }
 
     pub fn create_text_widget(&self, display: *mut xlib::Display, parent: xlib::Window, font: &str)
             -> Result<X11TextWidget, String> {
         X11TextWidget::new(display, parent, 0, 0, self.text_padding_horz, self.text_padding_vert,
-                               "".to_string(), font, self.foreground, self.inner_background)
+                               "".to_string(), font, self.foreground.pixel(), self.inner_background.pixel())
             .map_err(|e| e.to_string())
     }
 
     pub fn create_systray_widget(&self, display: *mut xlib::Display, parent: xlib::Window, parent_height: u32)
             -> Result<SystemTrayWidget, String> {
         SystemTrayWidget::new(display, parent, 0, 0, parent_height - 2 * self.spacing, self.padding_horz,
-                                     self.padding_horz, self.padding_vert, self.inner_background)
+                                     self.padding_horz, self.padding_vert, self.inner_background.pixel())
             .map_err(|e| e.to_string())
     }
 
+    fn resolve_colors(&mut self, color_by_name: &dyn Fn(&str) -> Option<u64>) -> Vec<String> {
+        [&mut self.foreground, &mut self.inner_background, &mut self.outer_background].into_iter()
+            .filter_map(|color| color.resolve(color_by_name).err())
+            .collect()
+    }
+
     fn default_status() -> Self {
         ContainerWidgetStyle {
-            foreground: 0x262626,
-            inner_background: 0xaf5f5f,
-            outer_background: 0x262626,
+            foreground: Color::from(0x262626),
+            inner_background: Color::from(0xaf5f5f),
+            outer_background: Color::from(0x262626),
             padding_horz: 4,
             padding_vert: 4,
             text_padding_horz: 5,
@@ -125,9 +145,9 @@ impl ContainerWidgetStyle {
 
     fn default_workspaces() -> Self {
         ContainerWidgetStyle {
-            foreground: 0x262626,
-            inner_background: 0x5f87af,
-            outer_background: 0x262626,
+            foreground: Color::from(0x262626),
+            inner_background: Color::from(0x5f87af),
+            outer_background: Color::from(0x262626),
             padding_horz: 0,
             padding_vert: 0,
             text_padding_horz: 10,
@@ -138,15 +158,35 @@ impl ContainerWidgetStyle {
 }
 
 impl TextWidgetStyle {
+    fn resolve_colors(&mut self, color_by_name: &dyn Fn(&str) -> Option<u64>) -> Vec<String> {
+        [&mut self.foreground, &mut self.background].into_iter()
+            .filter_map(|color| color.resolve(color_by_name).err())
+            .collect()
+    }
+
     pub fn create_text_widget(&self, display: *mut xlib::Display, parent: xlib::Window, font: &str)
             -> Result<X11TextWidget, String> {
         X11TextWidget::new(display, parent, 0, 0, self.padding_horz, self.padding_vert,
-                               "".to_string(), font, self.foreground, self.background)
+                               "".to_string(), font, self.foreground.pixel(), self.background.pixel())
             .map_err(|e| e.to_string())
     }
 }
 
 
+/// Read the theme and look up the named colors of the style
+pub fn load_colors(display: *mut xlib::Display, style: &mut BarStyle) {
+    let color_by_name = |name: &str| parse_color_name(display, name);
+    if let Err(msg) = load_theme(&color_by_name) {
+        println!("Unable to read theme: {}", msg);
+    }
+    for msg in style.resolve_colors(&color_by_name) {
+        println!("Unable to resolve color: {}", msg);
+    }
+}
+
+/// Read the configuration
+///
+/// Named colors are only looked up by [load_colors] once connected to the X server.
 pub fn read_config(overwrite_path: Option<path::PathBuf>) -> Configuration {
     let result = if let Some(path) = overwrite_path {
         read_file(&path)
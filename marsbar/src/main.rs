@@ -84,8 +84,8 @@ impl Bar {
         // TODO destroy window on failure
         let mut canvas = X11Canvas::new_for_window(display, window)
             .map_err(|err| unsafe { xlib::XDestroyWindow(display, window); err })?;
-        canvas.set_foreground(config.style.background)
-            .and(canvas.set_background(config.style.background))
+        canvas.set_foreground(config.style.background.pixel())
+            .and(canvas.set_background(config.style.background.pixel()))
             .and(canvas.set_font(&config.style.font))
             .map_err(|err| unsafe { xlib::XDestroyWindow(display, window); err })?;
 
@@ -195,7 +195,12 @@ impl Bar {
     }
 
     fn arrange_title(&mut self, title: String) {
-        self.title_widget.set_label(title);
+        // show active binding mode of the window manager in front of the title
+        let label = match self.get_binding_mode() {
+            Some(mode) => format!("[{}] {}", mode, title),
+            None => title,
+        };
+        self.title_widget.set_label(label);
         let height_diff = (self.dimensions.h() as i32 - self.title_widget.size().1 as i32) / 2;
         let x = (self.dimensions.w() as i32 - self.title_widget.size().0 as i32) / 2;
         let y = height_diff;
@@ -277,12 +282,17 @@ impl Bar {
             let height = self.dimensions.h();
             let x = (self.dimensions.x() + self.dimensions.w() as i32) - width as i32;
             let y = self.dimensions.y();
-            self.canvas.fill_rectangle_with(x, y, width, height, self.config.style.status.outer_background);
+            self.canvas.fill_rectangle_with(x, y, width, height, self.config.style.status.outer_background.pixel());
         }
 
         self.canvas.flush();
     }
 
+    fn get_binding_mode(&self) -> Option<String> {
+        let modes = self.root.x11_get_text_list_property(self.display, MarsBindingMode).ok()?;
+        modes.into_iter().next().filter(|mode| !mode.is_empty())
+    }
+
     fn get_status(&self) -> Result<String, String> {
         self.root.x11_read_property_string(self.display, MarsStatus)
             .or_else(|_| self.root.x11_wm_name(self.display))
@@ -374,7 +384,9 @@ impl Bar {
     fn handle_root_event(&mut self, event: xlib::XEvent) {
         if event.get_type() == xlib::PropertyNotify {
             let event = unsafe { event.property };
-            if let Some(property) = X11Atom::from_xlib_atom(self.display, event.atom) {
+            if event.atom == xlib::XA_RESOURCE_MANAGER {
+                self.restyle();
+            } else if let Some(property) = X11Atom::from_xlib_atom(self.display, event.atom) {
                 match property {
                     NetActiveWindow => {
                         // watch updates for WM_NAME on active window
@@ -411,6 +423,10 @@ impl Bar {
                         };
                         self.arrange_workspaces(workspace_names);
                     }
+                    MarsBindingMode => {
+                        let title = self.get_active_window_title();
+                        self.arrange_title(title);
+                    },
                     MarsStatus | WMName => {
                         if let Ok(status) = self.get_status() {
                             self.arrange_status(status)
@@ -431,6 +447,28 @@ impl Bar {
         self.arrange();
     }
 
+    /// Apply colors again after the X resources changed
+    fn restyle(&mut self) {
+        load_colors(self.display, &mut self.config.style);
+        let style = &self.config.style;
+
+        let _ = self.canvas.set_foreground(style.background.pixel())
+            .and(self.canvas.set_background(style.background.pixel()));
+        let _ = self.title_widget.set_foreground(style.title.foreground.pixel())
+            .and(self.title_widget.set_background(style.title.background.pixel()));
+        let _ = self.workspace_widget.set_background(style.workspaces.outer_background.pixel());
+        let _ = self.status_widget.set_background(style.status.outer_background.pixel());
+        for widget in self.status_widget.children_mut() {
+            let _ = widget.set_foreground(style.status.foreground.pixel())
+                .and(widget.set_background(style.status.inner_background.pixel()));
+        }
+
+        if let Ok(idx) = self.get_active_workspace() {
+            self.set_active_workspace(idx);
+        }
+        self.draw();
+    }
+
     fn update_constraints(&mut self) {
         let height = self.dimensions.h();
         let mut width = self.dimensions.w() - self.workspace_widget.size().0;
@@ -452,11 +490,11 @@ impl Bar {
     fn set_active_workspace(&mut self, new_idx: u32) {
         for (i, widget) in &mut self.workspace_widget.children_mut().enumerate() {
             if i as u32 == new_idx {
-                widget.set_foreground(self.config.style.workspaces.inner_background).unwrap();
-                widget.set_background(self.config.style.workspaces.foreground).unwrap();
+                widget.set_foreground(self.config.style.workspaces.inner_background.pixel()).unwrap();
+                widget.set_background(self.config.style.workspaces.foreground.pixel()).unwrap();
             } else {
-                widget.set_foreground(self.config.style.workspaces.foreground).unwrap();
-                widget.set_background(self.config.style.workspaces.inner_background).unwrap();
+                widget.set_foreground(self.config.style.workspaces.foreground.pixel()).unwrap();
+                widget.set_background(self.config.style.workspaces.inner_background.pixel()).unwrap();
             }
         }
     }
@@ -496,7 +534,7 @@ fn main() {
         std::process::exit(0);
     }
 
-    let config = read_config(args.config);
+    let mut config = read_config(args.config);
 
     unsafe {
         xlib::XSetErrorHandler(Some(on_error));
@@ -504,6 +542,7 @@ fn main() {
 
     // test();
     let display = libmars::common::x11::open_display().unwrap();
+    load_colors(display, &mut config.style);
 
     // unsafe {
     //     #[cfg(debug_assertions)]
@@ -7,6 +7,7 @@ pub struct Attributes {
     pub is_pinned: bool,
 
     pub floating_dimensions: Option<Dimensions>,
+    pub opacity: Option<f32>,
 }
 
 impl Default for Attributes {
@@ -17,6 +18,7 @@ impl Default for Attributes {
             is_pinned: false,
 
             floating_dimensions: None,
+            opacity: None,
         }
     }
 }
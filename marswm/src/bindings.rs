@@ -1,11 +1,14 @@
 extern crate x11;
 
-use libmars::common::x11::get_keysym;
-use serde::{Serialize, Deserialize};
+use libmars::common::x11::{get_keysym, sanitize_modifiers};
+use libmars::wm::KeyIdentifier;
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+use serde::de::{self, Visitor};
 use std::cell::RefCell;
+use std::fmt;
 use std::cmp;
 use std::rc::Rc;
-use x11::xlib::{Mod1Mask, Mod4Mask, ShiftMask, ControlMask};
+use x11::xlib::{AnyModifier, Mod1Mask, Mod2Mask, Mod3Mask, Mod4Mask, Mod5Mask, ShiftMask, ControlMask};
 
 use crate::*;
 use crate::layouts::*;
@@ -40,6 +43,8 @@ pub enum BindingAction {
     CenterClient,
     /// Change the ration between main and stack area
     ChangeMainRatio(f32),
+    /// Enter a binding mode for a single key press (for key chords)
+    Chord(String),
     /// Close the client
     CloseClient,
     /// Cycle through clients
@@ -52,14 +57,22 @@ pub enum BindingAction {
     CycleWorkspace(i32),
     /// Execute a command in the system shell
     Execute(String),
+    /// Enter a binding mode until it is left with `leave-mode` or Escape
+    EnterMode(String),
     /// Exit the window manager
     Exit,
     /// Switch between the last focused window of the main and stack area
     FocusMain,
+    /// Focus the previously focused client (switching workspaces and monitors if necessary)
+    FocusPrevious,
     /// Increase or decrease the gap width of the current workspace
     IncGaps(i32),
     /// Increase or decrease the number of clients in the main area
     IncNMain(i32),
+    /// Increase or decrease the opacity of the client
+    IncOpacity(f32),
+    /// Leave the current binding mode
+    LeaveMode,
     /// Move the client with the mouse
     MouseMove,
     /// Place a window with the mouse
@@ -68,6 +81,10 @@ pub enum BindingAction {
     MouseResize,
     /// Resize a window around its center
     MouseResizeCentered,
+    /// Move a floating client by the specified offset
+    MoveClient(i32, i32),
+    /// Move a floating client to a corner of the screen
+    MoveClientToCorner(Corner),
     /// Move client to/from the main area
     MoveMain,
     /// Move the client to a different monitor (relative to the current monitor)
@@ -76,14 +93,24 @@ pub enum BindingAction {
     MoveWorkspace(u32),
     /// Switch to the previously focused workspace
     PreviousWorkspace,
+    /// Resize a floating client by the specified amount
+    ResizeClient(i32, i32),
     /// Restart the window manager
     Restart,
+    /// Cycle through the clients of an application or execute a command if there are none
+    RunOrRaise { class: String, command: String },
+    /// Execute a list of actions one after another
+    Sequence(Vec<BindingAction>),
     /// Set the layout
     SetLayout(LayoutType),
+    /// Set the opacity of the client (0.0 - 1.0)
+    SetOpacity(f32),
     /// Set the stack mode for the dynamic layout
     SetStackMode(StackMode),
     /// Set the stack position for the dynamic layout
     SetStackPosition(StackPosition),
+    /// Move a floating client to an edge of the screen
+    SnapClient(Edge),
     /// Move the client up or down the stack
     StackMove(i32),
     /// Switch to a different workspace
@@ -92,14 +119,41 @@ pub enum BindingAction {
     ToggleFloating,
     /// Toggle fullscreen state on the window
     ToggleFullscreen,
+    /// Move the pointer to the center of the client
+    WarpPointerToClient,
+    /// Cycle through the clients of the workspace in most recently used order while the modifiers are held
+    WindowSwitcher(i32),
+}
+
+#[derive(Serialize,Deserialize,Clone,Copy,Debug,PartialEq,Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+#[derive(Serialize,Deserialize,Clone,Copy,Debug,PartialEq,Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Edge {
+    Top,
+    Bottom,
+    Left,
+    Right,
 }
 
 #[derive(Serialize,Deserialize,Clone,Debug,PartialEq,Eq)]
 pub enum Modifier {
     Mod1,
+    /// ignored when matching bindings, as it is usually NumLock
+    Mod2,
+    Mod3,
     Mod4,
+    Mod5,
     Shift,
     Control,
+    Any,
 }
 
 #[derive(Serialize,Deserialize,PartialEq,Debug,Clone)]
@@ -111,10 +165,25 @@ pub struct KeyBinding {
     /// key name (as found in
     /// [keysymdef.h](https://cgit.freedesktop.org/xorg/proto/x11proto/tree/keysymdef.h) without
     /// the leading "XK_")
+    #[serde(default)]
     key: String,
 
-    /// action to execute on key press
-    action: BindingAction,
+    /// key code (takes precedence over the key name and does not depend on the keyboard layout)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    keycode: Option<u32>,
+
+    /// execute action on key release instead of key press
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    on_release: bool,
+
+    /// action or list of actions to execute on key press
+    #[serde(rename = "action", alias = "actions")]
+    #[serde(serialize_with = "serialize_actions", deserialize_with = "deserialize_actions")]
+    actions: Vec<BindingAction>,
+
+    /// binding mode in which this binding is active (active outside of modes if not set)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mode: Option<String>,
 }
 
 #[derive(Serialize,Deserialize,PartialEq,Debug,Clone)]
@@ -128,8 +197,10 @@ pub struct ButtonBinding {
     /// button target
     targets: Vec<ButtonTarget>,
 
-    /// action to execute on key press
-    action: BindingAction,
+    /// action or list of actions to execute on button press
+    #[serde(rename = "action", alias = "actions")]
+    #[serde(serialize_with = "serialize_actions", deserialize_with = "deserialize_actions")]
+    actions: Vec<BindingAction>,
 }
 
 impl BindingAction {
@@ -144,23 +215,28 @@ impl BindingAction {
             CloseClient => if let Some(client_rc) = client_option {
                 client_rc.borrow().close();
             },
+            Chord(mode) => wm.set_binding_mode(backend, Some(mode.clone()), true),
             CycleClient(inc) => wm.cycle_client(backend, *inc),
-            CycleLayout => wm.current_workspace_mut(backend).cycle_layout(),
+            CycleLayout => {
+                wm.current_workspace_mut(backend).cycle_layout();
+                wm.refresh_allowed_actions();
+                wm.layout_changed(backend);
+            },
             CycleMonitor(inc) => wm.cycle_monitor(backend, *inc),
             CycleWorkspace(inc) => wm.cycle_workspace(backend, *inc),
-            Execute(cmd) => {
-                if let Ok(mut handle) = std::process::Command::new("sh").arg("-c").arg(cmd).spawn() {
-                    std::thread::spawn(move || {
-                        let _ignored = handle.wait();
-                    });
-                }
-            },
+            Execute(cmd) => execute_shell(cmd),
+            EnterMode(mode) => wm.set_binding_mode(backend, Some(mode.clone()), false),
             Exit => {
                 wm.exit(backend);
             },
             FocusMain => wm.switch_to_main(backend),
+            FocusPrevious => wm.focus_previous(backend),
             IncGaps(i) => wm.current_workspace_mut(backend).inc_gaps(*i),
             IncNMain(i) => wm.current_workspace_mut(backend).inc_nmain(*i),
+            IncOpacity(inc) => if let Some(client_rc) = client_option {
+                wm.inc_opacity(client_rc, *inc);
+            },
+            LeaveMode => wm.set_binding_mode(backend, None, false),
             MouseMove => if let Some(client_rc) = client_option {
                 backend.mouse_move(wm, client_rc);
                 wm.current_monitor_mut(backend).restack_current();
@@ -178,6 +254,12 @@ impl BindingAction {
                     wm.mouse_resize_centered(backend, client_rc);
                 }
             },
+            MoveClient(dx, dy) => if let Some(client_rc) = client_option {
+                wm.move_client(client_rc, *dx, *dy);
+            },
+            MoveClientToCorner(corner) => if let Some(client_rc) = client_option {
+                wm.move_client_to_corner(client_rc, *corner);
+            },
             MoveMonitor(inc) => if let Some(client_rc) = client_option {
                 wm.move_client_to_monitor(client_rc, *inc);
             },
@@ -194,12 +276,32 @@ impl BindingAction {
             MoveMain => if let Some(client_rc) = client_option {
                 wm.current_workspace_mut(backend).move_main(client_rc);
             },
+            ResizeClient(dw, dh) => if let Some(client_rc) = client_option {
+                wm.resize_client(client_rc, *dw, *dh);
+            },
             Restart => wm.restart(backend),
-            SetLayout(layout) => wm.current_workspace_mut(backend).set_layout(*layout),
+            RunOrRaise { class, command } => if !wm.cycle_application(backend, class) {
+                execute_shell(command);
+            },
+            Sequence(actions) => for action in actions {
+                action.execute(wm, backend, client_option.clone());
+            },
+            SetLayout(layout) => {
+                wm.current_workspace_mut(backend).set_layout(*layout);
+                wm.refresh_allowed_actions();
+                wm.layout_changed(backend);
+            },
+            SetOpacity(opacity) => if let Some(client_rc) = client_option {
+                wm.set_opacity(client_rc, *opacity);
+            },
             SetStackMode(mode) => wm.current_workspace_mut(backend).set_stack_mode(*mode),
             SetStackPosition(position) => wm.current_workspace_mut(backend).set_stack_position(*position),
+            SnapClient(edge) => if let Some(client_rc) = client_option {
+                wm.snap_client(client_rc, *edge);
+            },
             StackMove(i) => if let Some(client_rc) = client_option {
-                wm.current_workspace_mut(backend).stack_move(client_rc, *i);
+                wm.current_workspace_mut(backend).stack_move(client_rc.clone(), *i);
+                wm.warp_pointer_to_client(backend, &client_rc);
             },
             SwitchWorkspace(ws) => {
                 let ws_index_option = wm.current_monitor(backend).workspace(*ws)
@@ -214,21 +316,45 @@ impl BindingAction {
             ToggleFullscreen => if let Some(client_rc) = client_option {
                 wm.toggle_fullscreen_client(backend, client_rc);
             },
+            WarpPointerToClient => if let Some(client_rc) = client_option {
+                client_rc.borrow().warp_pointer_to_center();
+            },
+            WindowSwitcher(inc) => wm.switch_window(backend, *inc),
         }
     }
 }
 
 impl KeyBinding {
     pub fn new(modifiers: Vec<Modifier>, key: &str, action: BindingAction) -> Self {
-        KeyBinding { modifiers, key: key.to_owned(), action }
+        KeyBinding { modifiers, key: key.to_owned(), keycode: None, on_release: false, actions: vec![action], mode: None }
+    }
+
+    pub fn actions(&self) -> &Vec<BindingAction> {
+        &self.actions
+    }
+
+    pub fn identifier(&self) -> KeyIdentifier {
+        match self.keycode {
+            Some(keycode) => KeyIdentifier::Keycode(keycode),
+            None => KeyIdentifier::Keysym(self.key()),
+        }
+    }
+
+    /// XF86 keys (like media keys) are bound globally
+    pub fn is_global(&self) -> bool {
+        self.keycode.is_none() && self.key.starts_with("XF86")
     }
 
-    pub fn action(&self) -> BindingAction {
-        self.action.clone()
+    pub fn matches(&self, modifiers: u32, keycode: u32, keysym: u32, release: bool) -> bool {
+        let key_matches = match self.keycode {
+            Some(binding_keycode) => keycode == binding_keycode,
+            None => keysym == self.key(),
+        };
+        release == self.on_release && modifiers_match(self.modifiers(), modifiers) && key_matches
     }
 
-    pub fn matches(&self, modifiers: u32, key: u32) -> bool {
-        modifiers == self.modifiers() && key == self.key()
+    pub fn mode(&self) -> Option<&str> {
+        self.mode.as_deref()
     }
 
     pub fn modifiers(&self) -> u32 {
@@ -238,15 +364,27 @@ impl KeyBinding {
     pub fn key(&self) -> u32 {
         get_keysym(&self.key) as u32
     }
+
+    pub fn key_name(&self) -> &str {
+        &self.key
+    }
+
+    pub fn keycode(&self) -> Option<u32> {
+        self.keycode
+    }
+
+    pub fn on_release(&self) -> bool {
+        self.on_release
+    }
 }
 
 impl ButtonBinding {
     pub fn new(modifiers: Vec<Modifier>, button: u32, targets: Vec<ButtonTarget>, action: BindingAction) -> Self {
-        ButtonBinding { modifiers, button, targets, action }
+        ButtonBinding { modifiers, button, targets, actions: vec![action] }
     }
 
-    pub fn action(&self) -> BindingAction {
-        self.action.clone()
+    pub fn actions(&self) -> &Vec<BindingAction> {
+        &self.actions
     }
 
     pub fn button(&self) -> u32 {
@@ -254,7 +392,7 @@ impl ButtonBinding {
     }
 
     pub fn matches(&self, modifiers: u32, button: u32, target: ButtonTarget) -> bool {
-        return modifiers == self.modifiers() && button == self.button && self.targets.iter().any(|t| *t == target);
+        return modifiers_match(self.modifiers(), modifiers) && button == self.button && self.targets.iter().any(|t| *t == target);
     }
 
     pub fn modifiers(&self) -> u32 {
@@ -270,13 +408,73 @@ impl Modifier {
     pub fn mask(&self) -> u32 {
         match self {
             Modifier::Mod1 => Mod1Mask,
+            Modifier::Mod2 => Mod2Mask,
+            Modifier::Mod3 => Mod3Mask,
             Modifier::Mod4 => Mod4Mask,
+            Modifier::Mod5 => Mod5Mask,
             Modifier::Shift => ShiftMask,
             Modifier::Control => ControlMask,
+            Modifier::Any => AnyModifier,
         }
     }
 }
 
+/// Serialize a single action as such and multiple actions as a list
+pub fn serialize_actions<S: Serializer>(actions: &[BindingAction], serializer: S) -> Result<S::Ok, S::Error> {
+    match actions {
+        [action] => action.serialize(serializer),
+        actions => actions.serialize(serializer),
+    }
+}
+
+/// Deserialize either a single action or a list of actions
+pub fn deserialize_actions<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<BindingAction>, D::Error> {
+    struct ActionsVisitor;
+
+    impl<'de> Visitor<'de> for ActionsVisitor {
+        type Value = Vec<BindingAction>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("an action or a list of actions")
+        }
+
+        fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+            let action = BindingAction::deserialize(de::value::StrDeserializer::new(value))?;
+            Ok(vec![action])
+        }
+
+        fn visit_enum<A: de::EnumAccess<'de>>(self, data: A) -> Result<Self::Value, A::Error> {
+            let action = BindingAction::deserialize(de::value::EnumAccessDeserializer::new(data))?;
+            Ok(vec![action])
+        }
+
+        fn visit_map<A: de::MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+            let action = BindingAction::deserialize(de::value::MapAccessDeserializer::new(map))?;
+            Ok(vec![action])
+        }
+
+        fn visit_seq<A: de::SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+            Vec::deserialize(de::value::SeqAccessDeserializer::new(seq))
+        }
+    }
+
+    deserializer.deserialize_any(ActionsVisitor)
+}
+
+/// Execute a command in the system shell without blocking the window manager
+fn execute_shell(cmd: &str) {
+    if let Ok(mut handle) = std::process::Command::new("sh").arg("-c").arg(cmd).spawn() {
+        std::thread::spawn(move || {
+            let _ignored = handle.wait();
+        });
+    }
+}
+
+/// Compare modifiers of a binding with those of an event, ignoring NumLock (usually Mod2)
+fn modifiers_match(binding_modifiers: u32, modifiers: u32) -> bool {
+    binding_modifiers & AnyModifier != 0 || sanitize_modifiers(binding_modifiers) == modifiers
+}
+
 pub fn default_key_bindings(nworkspaces: u32) -> Vec<KeyBinding> {
     use BindingAction::*;
     use Modifier::*;
@@ -352,6 +550,9 @@ pub fn default_button_bindings() -> Vec<ButtonBinding> {
         client_button_binding!(4, StackMove(-1), (Shift)),
         client_button_binding!(5, CycleClient(1)),
         client_button_binding!(5, StackMove(1), (Shift)),
+        ButtonBinding::new(vec![], 3, vec![Root], Execute("mars-relay menu".to_owned())),
+        ButtonBinding::new(vec![], 4, vec![Root], CycleWorkspace(-1)),
+        ButtonBinding::new(vec![], 5, vec![Root], CycleWorkspace(1)),
     ];
     bindings
 }
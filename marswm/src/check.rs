@@ -0,0 +1,251 @@
+//! Validation of the configuration files (`luna --check-config`).
+
+use libmars::common::x11::{close_display, open_display, parse_color_name, sanitize_modifiers};
+use libmars::utils::configuration::*;
+use libmars::utils::theme::*;
+use serde::Deserialize;
+use std::cell::RefCell;
+use std::cmp;
+use std::fs;
+use std::path::Path;
+use x11::xlib::{AnyModifier, Mod2Mask};
+
+use crate::bindings::*;
+use crate::config::*;
+use crate::rules::*;
+
+
+/// An item read from a configuration file together with its origin
+struct Entry<T> {
+    file: String,
+    index: usize,
+    line: Option<usize>,
+    item: T,
+}
+
+#[derive(Default)]
+struct Report {
+    problems: Vec<String>,
+}
+
+
+impl<T> Entry<T> {
+    fn location(&self) -> String {
+        match self.line {
+            Some(line) => format!("{}:{}", self.file, line),
+            None => format!("{} (entry {})", self.file, self.index + 1),
+        }
+    }
+}
+
+impl Report {
+    fn problem(&mut self, location: &str, msg: String) {
+        self.problems.push(format!("{}: {}", location, msg));
+    }
+}
+
+
+/// Check all configuration files and print problems to stderr
+///
+/// Returns true if no problems were found.
+pub fn check_config() -> bool {
+    let mut report = Report::default();
+
+    // X11 color names can only be verified with a connection to the X server
+    let display = open_display().ok();
+    let unverified = RefCell::new(Vec::new());
+    let color_by_name = |name: &str| match display {
+        Some(display) => parse_color_name(display, name),
+        None => {
+            unverified.borrow_mut().push(name.to_owned());
+            Some(0)
+        },
+    };
+
+    if let Err(msg) = load_theme(&color_by_name) {
+        report.problem(THEME_FILE, msg);
+    }
+
+    let mut config = match read_entries::<Configuration>(&mut report, CONFIG_FILE) {
+        Some((_, Some(config))) => config,
+        _ => Configuration::default(),
+    };
+    for msg in config.theming.resolve_colors(&color_by_name) {
+        report.problem(&config_path(CONFIG_FILE), msg);
+    }
+    if let Some(display) = display {
+        close_display(display);
+    }
+    let nworkspaces = cmp::max(config.primary_workspaces, config.secondary_workspaces);
+    for title_button in &config.theming.title_buttons {
+        check_actions(&mut report, &config_path(CONFIG_FILE), &title_button.actions, nworkspaces);
+    }
+
+    let key_bindings = read_list(&mut report, KEY_BINDINGS_FILE, KEY_BINDINGS_EXT_FILE,
+                                 || default_key_bindings(config.primary_workspaces), "default key bindings");
+    let button_bindings = read_list(&mut report, BUTTON_BINDINGS_FILE, BUTTON_BINDINGS_EXT_FILE,
+                                    default_button_bindings, "default button bindings");
+    let rules = match read_entries::<Vec<Rule>>(&mut report, RULES_FILE) {
+        Some((lines, Some(rules))) => with_lines(&config_path(RULES_FILE), lines, rules),
+        _ => Vec::new(),
+    };
+
+    check_key_bindings(&mut report, &key_bindings, nworkspaces);
+    check_button_bindings(&mut report, &button_bindings, nworkspaces);
+    check_rules(&mut report, &rules, nworkspaces);
+
+    for problem in &report.problems {
+        eprintln!("{}", problem);
+    }
+    if !unverified.borrow().is_empty() {
+        eprintln!("Unable to verify color names without X server: {}", unverified.borrow().join(", "));
+    }
+    if report.problems.is_empty() {
+        println!("No problems found");
+    }
+
+    report.problems.is_empty()
+}
+
+fn check_actions(report: &mut Report, location: &str, actions: &[BindingAction], nworkspaces: u32) {
+    for action in actions {
+        match action {
+            BindingAction::MoveWorkspace(ws) | BindingAction::SwitchWorkspace(ws) if *ws >= nworkspaces => {
+                report.problem(location, format!("workspace index {} is out of range (only {} workspaces)", ws, nworkspaces));
+            },
+            BindingAction::Sequence(actions) => check_actions(report, location, actions, nworkspaces),
+            _ => (),
+        }
+    }
+}
+
+fn check_button_bindings(report: &mut Report, bindings: &[Entry<ButtonBinding>], nworkspaces: u32) {
+    for (i, entry) in bindings.iter().enumerate() {
+        let binding = &entry.item;
+        check_actions(report, &entry.location(), binding.actions(), nworkspaces);
+        check_modifiers(report, &entry.location(), binding.modifiers());
+
+        let conflict = bindings[..i].iter().find(|other| {
+            modifiers_conflict(binding.modifiers(), other.item.modifiers())
+                && binding.button() == other.item.button()
+                && binding.targets().iter().any(|t| other.item.targets().contains(t))
+        });
+        if let Some(other) = conflict {
+            report.problem(&entry.location(), format!("button binding conflicts with binding at {}", other.location()));
+        }
+    }
+}
+
+fn check_key_bindings(report: &mut Report, bindings: &[Entry<KeyBinding>], nworkspaces: u32) {
+    for (i, entry) in bindings.iter().enumerate() {
+        let binding = &entry.item;
+        check_actions(report, &entry.location(), binding.actions(), nworkspaces);
+        check_modifiers(report, &entry.location(), binding.modifiers());
+
+        if binding.keycode().is_none() {
+            if binding.key_name().is_empty() {
+                report.problem(&entry.location(), "neither key nor keycode specified".to_owned());
+                continue;
+            } else if binding.key() == 0 {
+                report.problem(&entry.location(), format!("unknown key '{}'", binding.key_name()));
+                continue;
+            }
+        }
+
+        let conflict = bindings[..i].iter().find(|other| {
+            modifiers_conflict(binding.modifiers(), other.item.modifiers())
+                && binding.identifier() == other.item.identifier()
+                && binding.on_release() == other.item.on_release()
+                && binding.mode() == other.item.mode()
+        });
+        if let Some(other) = conflict {
+            report.problem(&entry.location(), format!("key binding conflicts with binding at {}", other.location()));
+        }
+    }
+}
+
+fn check_modifiers(report: &mut Report, location: &str, modifiers: u32) {
+    if modifiers & AnyModifier == 0 && modifiers & Mod2Mask != 0 {
+        report.problem(location, "Mod2 is ignored, as it is usually NumLock".to_owned());
+    }
+}
+
+fn check_rules(report: &mut Report, rules: &[Entry<Rule>], nworkspaces: u32) {
+    for entry in rules {
+        check_actions(report, &entry.location(), entry.item.actions(), nworkspaces);
+        if let Some(ws) = entry.item.workspace() {
+            if ws >= nworkspaces {
+                report.problem(&entry.location(), format!("workspace index {} is out of range (only {} workspaces)", ws, nworkspaces));
+            }
+        }
+    }
+}
+
+/// Line numbers of the top level list entries in a YAML file
+fn entry_lines(content: &str) -> Vec<usize> {
+    let items: Vec<(usize, usize)> = content.lines().enumerate()
+        .filter(|(_, line)| line.trim_start().starts_with('-') && !line.trim_start().starts_with("---"))
+        .map(|(i, line)| (i + 1, line.len() - line.trim_start().len()))
+        .collect();
+    let indent = items.iter().map(|(_, indent)| *indent).min();
+    items.iter().filter(|(_, i)| Some(*i) == indent).map(|(line, _)| *line).collect()
+}
+
+/// Whether two bindings match the same modifiers at runtime
+fn modifiers_conflict(a: u32, b: u32) -> bool {
+    a & AnyModifier != 0 || b & AnyModifier != 0 || sanitize_modifiers(a) == sanitize_modifiers(b)
+}
+
+/// Read a file, reporting parse errors
+///
+/// Returns `None` if the file does not exist. Otherwise the line numbers of the top level list
+/// entries and the content of the file (if it could be parsed) are returned.
+fn read_entries<T: for<'a> Deserialize<'a>>(report: &mut Report, file_name: &str) -> Option<(Vec<usize>, Option<T>)> {
+    let path = match find_config_file(CONFIG_DIR, file_name) {
+        Ok(Some(path)) => path,
+        Ok(None) => return None,
+        Err(msg) => {
+            report.problem(file_name, msg);
+            return None;
+        },
+    };
+    let lines = fs::read_to_string(&path).map(|content| entry_lines(&content)).unwrap_or_default();
+
+    match read_file(Path::new(&path)) {
+        Ok(content) => Some((lines, Some(content))),
+        Err(msg) => {
+            report.problem(&path.to_string_lossy(), msg);
+            Some((lines, None))
+        },
+    }
+}
+
+/// Read a list of bindings the same way as the window manager does
+///
+/// The bindings in `file_name` replace the defaults, while those in `ext_file_name` extend them.
+fn read_list<T: for<'a> Deserialize<'a>>(report: &mut Report, file_name: &str, ext_file_name: &str,
+                                          defaults: impl Fn() -> Vec<T>, defaults_name: &str) -> Vec<Entry<T>> {
+    let mut entries = match read_entries::<Vec<T>>(report, file_name) {
+        Some((lines, Some(items))) => with_lines(&config_path(file_name), lines, items),
+        _ => with_lines(defaults_name, Vec::new(), defaults()),
+    };
+    if let Some((lines, Some(items))) = read_entries::<Vec<T>>(report, ext_file_name) {
+        entries.extend(with_lines(&config_path(ext_file_name), lines, items));
+    }
+    entries
+}
+
+fn config_path(file_name: &str) -> String {
+    match find_config_file(CONFIG_DIR, file_name) {
+        Ok(Some(path)) => path.to_string_lossy().to_string(),
+        _ => file_name.to_owned(),
+    }
+}
+
+fn with_lines<T>(file: &str, lines: Vec<usize>, items: Vec<T>) -> Vec<Entry<T>> {
+    // only trust the line numbers if they match up with the parsed entries
+    let lines_valid = lines.len() == items.len();
+    items.into_iter().enumerate()
+        .map(|(index, item)| Entry { file: file.to_owned(), index, line: lines.get(index).filter(|_| lines_valid).copied(), item })
+        .collect()
+}
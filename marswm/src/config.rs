@@ -1,5 +1,7 @@
 use std::cmp;
 use libmars::common::*;
+use libmars::common::color::Color;
+use libmars::draw::TextAlignment;
 use serde::{Serialize, Deserialize};
 use libmars::utils::configuration::*;
 
@@ -9,13 +11,13 @@ use crate::layouts::StackMode;
 use crate::layouts::StackPosition;
 use crate::rules::*;
 
-const BUTTON_BINDINGS_FILE: &str = "buttonbindings";
-const BUTTON_BINDINGS_EXT_FILE: &str = "buttonbindings_ext";
-const CONFIG_DIR: &str = "luna";
-const CONFIG_FILE: &str = "luna";
-const KEY_BINDINGS_FILE: &str = "keybindings";
-const KEY_BINDINGS_EXT_FILE: &str = "keybindings_ext";
-const RULES_FILE: &str = "rules";
+pub const BUTTON_BINDINGS_FILE: &str = "buttonbindings";
+pub const BUTTON_BINDINGS_EXT_FILE: &str = "buttonbindings_ext";
+pub const CONFIG_DIR: &str = "luna";
+pub const CONFIG_FILE: &str = "luna";
+pub const KEY_BINDINGS_FILE: &str = "keybindings";
+pub const KEY_BINDINGS_EXT_FILE: &str = "keybindings_ext";
+pub const RULES_FILE: &str = "rules";
 const AUTOSTART_SCRIPT: &str = "autostart";
 const FILE_EXT: [&str; 2] = [".toml", ".yaml"];
 
@@ -35,6 +37,18 @@ pub struct Configuration {
     /// where should windows be placed initially
     pub initial_placement: WindowPlacement,
 
+    /// how windows get focused by the pointer
+    pub focus_model: FocusModel,
+
+    /// which newly mapped windows should get focused
+    pub focus_new_windows: FocusNewWindows,
+
+    /// how activation requests by applications are handled
+    pub activation_requests: ActivationPolicy,
+
+    /// when the pointer should be moved to windows
+    pub pointer_warp: PointerWarp,
+
     /// layout configuration
     pub layout: LayoutConfiguration,
 
@@ -64,21 +78,27 @@ pub struct LayoutConfiguration {
     pub stack_mode: StackMode,
 }
 
-#[derive(Serialize,Deserialize,PartialEq,Eq,Debug,Clone)]
+#[derive(Serialize,Deserialize,PartialEq,Debug,Clone)]
 #[serde(default)]
 pub struct ThemingConfiguration {
     /// color for active window frame
-    pub active_color: u64,
+    pub active_color: Color,
 
     /// color for passive window frame
-    pub inactive_color: u64,
+    pub inactive_color: Color,
 
     /// color of inner and outer border
-    pub border_color: u64,
+    pub border_color: Color,
 
     /// use inverted version of active/inactive color for border
     pub invert_border_color: bool,
 
+    /// opacity of the active window (0.0 - 1.0)
+    pub active_opacity: f32,
+
+    /// opacity of passive windows (0.0 - 1.0)
+    pub inactive_opacity: f32,
+
     /// width of the frame that client windows are reparented to
     pub frame_width: (u32, u32, u32, u32),
 
@@ -102,6 +122,24 @@ pub struct ThemingConfiguration {
 
     /// font to use for title
     pub font: String,
+
+    /// alignment of the title
+    pub title_alignment: TextAlignment,
+
+    /// template for the title ({class} and {title} are replaced by the client's values)
+    pub title_format: String,
+
+    /// title color of the active window (defaults to the inactive color)
+    pub active_title_color: Option<Color>,
+
+    /// title color of passive windows (defaults to the active color)
+    pub inactive_title_color: Option<Color>,
+
+    /// title color of urgent windows (defaults to the title color of passive windows)
+    pub urgent_title_color: Option<Color>,
+
+    /// buttons shown at the right of the title bar
+    pub title_buttons: Vec<TitleButton>,
 }
 
 #[derive(Serialize,Deserialize,PartialEq,Eq,Debug,Clone)]
@@ -117,6 +155,67 @@ pub struct NoDecorThemingConfiguration {
     pub outer_border_width: u32,
 }
 
+#[derive(Serialize,Deserialize,PartialEq,Debug,Clone)]
+pub struct TitleButton {
+    /// text or glyph shown on the button
+    pub glyph: String,
+
+    /// action or list of actions to execute when the button is clicked
+    #[serde(rename = "action", alias = "actions")]
+    #[serde(serialize_with = "serialize_actions", deserialize_with = "deserialize_actions")]
+    pub actions: Vec<BindingAction>,
+}
+
+#[derive(Serialize,Deserialize,Clone,Copy,Debug,Default,PartialEq,Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum FocusModel {
+    /// focus follows the pointer
+    #[default]
+    Sloppy,
+    /// windows are focused by clicking on them or by key bindings that move the pointer
+    Click,
+    /// the pointer only focuses windows by clicking on them (key bindings focus directly)
+    StrictClick,
+}
+
+#[derive(Serialize,Deserialize,Clone,Copy,Debug,Default,PartialEq,Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum FocusNewWindows {
+    /// always focus new windows
+    #[default]
+    Always,
+    /// never focus new windows
+    Never,
+    /// only focus new windows of the application that is currently focused
+    SameApplication,
+    /// focus new windows if nothing is focused, or they are dialogs or of the same application
+    Smart,
+}
+
+#[derive(Serialize,Deserialize,Clone,Copy,Debug,Default,PartialEq,Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ActivationPolicy {
+    /// applications may always activate their windows
+    Allow,
+    /// only allow one activation per second, mark the window as urgent otherwise
+    #[default]
+    RateLimit,
+    /// mark the window as urgent instead of activating it
+    Urgency,
+}
+
+#[derive(Serialize,Deserialize,Clone,Copy,Debug,Default,PartialEq,Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum PointerWarp {
+    /// never move the pointer (except when switching to a workspace on another monitor)
+    Never,
+    /// only move the pointer if the window is on a different monitor
+    OnMonitorChange,
+    /// always move the pointer to the window
+    #[default]
+    Always,
+}
+
 #[derive(Serialize,Deserialize,Clone,Copy,Debug,PartialEq,Eq)]
 #[serde(rename_all = "kebab-case")]
 pub enum WindowPlacement {
@@ -147,6 +246,10 @@ impl Default for Configuration {
             secondary_workspaces: 9,
             on_startup: autostart_path,
             initial_placement: WindowPlacement::default(),
+            focus_model: FocusModel::default(),
+            focus_new_windows: FocusNewWindows::default(),
+            activation_requests: ActivationPolicy::default(),
+            pointer_warp: PointerWarp::default(),
             layout: LayoutConfiguration::default(),
             theming: ThemingConfiguration::default(),
         }
@@ -169,10 +272,12 @@ impl Default for LayoutConfiguration {
 impl Default for ThemingConfiguration {
     fn default() -> Self {
         ThemingConfiguration {
-            active_color: 0x30d6ff,
-            inactive_color: 0x141414,
-            border_color: 0x141414,
+            active_color: Color::from(0x30d6ff),
+            inactive_color: Color::from(0x141414),
+            border_color: Color::from(0x141414),
             invert_border_color: false,
+            active_opacity: 1.0,
+            inactive_opacity: 1.0,
             frame_width: (10, 1, 1, 1),
             inner_border_width: 0,
             outer_border_width: 0,
@@ -181,6 +286,12 @@ impl Default for ThemingConfiguration {
             title_vpadding: 0,
             title_hpadding: 5,
             font: "sans".to_owned(),
+            title_alignment: TextAlignment::Left,
+            title_format: "{title}".to_owned(),
+            active_title_color: None,
+            inactive_title_color: None,
+            urgent_title_color: None,
+            title_buttons: Vec::new(),
         }
     }
 }
@@ -202,6 +313,17 @@ impl Default for WindowPlacement {
 }
 
 
+impl ThemingConfiguration {
+    /// Look up named colors (see [Color::resolve]) and return the ones that cannot be resolved
+    pub fn resolve_colors(&mut self, color_by_name: &dyn Fn(&str) -> Option<u64>) -> Vec<String> {
+        let title_colors = [&mut self.active_title_color, &mut self.inactive_title_color, &mut self.urgent_title_color];
+        [&mut self.active_color, &mut self.inactive_color, &mut self.border_color].into_iter()
+            .chain(title_colors.into_iter().flatten())
+            .filter_map(|color| color.resolve(color_by_name).err())
+            .collect()
+    }
+}
+
 impl WindowPlacement {
     pub fn calc(&self, client_dimensions: Dimensions, window_area: Dimensions, pointer: (i32, i32)) -> (i32, i32) {
         use WindowPlacement::*;
@@ -248,6 +370,9 @@ pub fn read_button_bindings() -> Vec<ButtonBinding> {
     button_bindings
 }
 
+/// Read the configuration
+///
+/// Named colors are only looked up once the window manager is connected to the X server.
 pub fn read_config() -> Configuration {
     return match read_config_file(CONFIG_DIR, CONFIG_FILE) {
         Ok(config) => config,
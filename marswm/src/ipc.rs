@@ -0,0 +1,229 @@
+//! IPC socket to control the window manager and query its state.
+
+extern crate x11;
+
+use libmars::common::Dimensioned;
+use libmars::common::x11::atoms::X11Atom;
+use libmars::common::x11::send_client_message;
+use libmars::control::{ClientInfo, LayoutInfo, MonitorInfo, WMEvent, WorkspaceInfo};
+use libmars::control::ipc::*;
+use libmars::wm::{ Backend, Client, WindowManager };
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::ptr;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use x11::xlib;
+
+use crate::*;
+use crate::layouts::LayoutType;
+
+
+/// Time a client may take to send its request or receive the response
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+type Request = (IpcRequest<BindingAction>, UnixStream);
+
+/// Accepts requests on the IPC socket and queues them for the window manager
+///
+/// The window manager is notified about new requests with a `_MARS_IPC` client message.
+pub struct IpcServer {
+    path: PathBuf,
+    receiver: mpsc::Receiver<Request>,
+    subscribers: Vec<UnixStream>,
+}
+
+
+impl IpcServer {
+    pub fn start() -> Option<IpcServer> {
+        let path = socket_path();
+        if let Err(e) = create_socket_dir() {
+            eprintln!("Unable to create IPC socket {}: {}", path.to_string_lossy(), e);
+            return None;
+        }
+        // remove stale socket of a previous instance
+        let _ = fs::remove_file(&path);
+        let listener = match UnixListener::bind(&path) {
+            Ok(listener) => listener,
+            Err(e) => {
+                eprintln!("Unable to create IPC socket {}: {}", path.to_string_lossy(), e);
+                return None;
+            },
+        };
+        // anyone able to connect can execute commands, so restrict access to the current user
+        if let Err(e) = fs::set_permissions(&path, fs::Permissions::from_mode(0o600)) {
+            eprintln!("Unable to restrict access to IPC socket {}: {}", path.to_string_lossy(), e);
+            let _ = fs::remove_file(&path);
+            return None;
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || Self::listen(listener, sender));
+        Some(IpcServer { path, receiver, subscribers: Vec::new() })
+    }
+
+    /// Requests that have been received since the last call
+    pub fn pending(&self) -> Vec<Request> {
+        self.receiver.try_iter().collect()
+    }
+
+    /// Send an event to all subscribers, dropping those that are gone
+    pub fn publish(&mut self, event: &WMEvent) {
+        let mut line = match serde_json::to_string(event) {
+            Ok(line) => line,
+            Err(_) => return,
+        };
+        line.push('\n');
+        self.subscribers.retain_mut(|stream| stream.write_all(line.as_bytes()).is_ok());
+    }
+
+    pub fn shutdown(&self) {
+        let _ = fs::remove_file(&self.path);
+    }
+
+    /// Keep the connection open to send events to it
+    pub fn subscribe(&mut self, mut stream: UnixStream) {
+        respond(&mut stream, IpcResponse::Ok(serde_json::Value::Null));
+        // never block the window manager on slow subscribers
+        if stream.set_nonblocking(true).is_ok() {
+            self.subscribers.push(stream);
+        }
+    }
+
+    fn listen(listener: UnixListener, sender: mpsc::Sender<Request>) {
+        let display = unsafe { xlib::XOpenDisplay(ptr::null()) };
+        if display.is_null() {
+            eprintln!("Unable to open display for IPC socket");
+            return;
+        }
+
+        for mut stream in listener.incoming().flatten() {
+            // connections are handled one after another, so a stalled client must not block the others
+            if stream.set_read_timeout(Some(TIMEOUT)).is_err() || stream.set_write_timeout(Some(TIMEOUT)).is_err() {
+                continue;
+            }
+
+            let mut line = String::new();
+            if BufReader::new(&stream).read_line(&mut line).is_err() {
+                continue;
+            }
+
+            match serde_json::from_str(&line) {
+                Ok(request) => {
+                    if sender.send((request, stream)).is_err() {
+                        return;
+                    }
+                    send_client_message(display, X11Atom::MarsIpc, 0, xlib::ClientMessageData::new());
+                },
+                Err(e) => respond(&mut stream, IpcResponse::Error(e.to_string())),
+            }
+        }
+    }
+}
+
+
+/// Execute an action or answer a query
+pub fn handle_request<B: Backend<Attributes>>(wm: &mut MarsWM<B>, backend: &mut B, request: IpcRequest<BindingAction>)
+        -> IpcResponse {
+    let client_option = match request.window {
+        Some(window) => match wm.clients().find(|c| c.borrow().id() == window) {
+            Some(client_rc) => Some(client_rc.clone()),
+            None => return IpcResponse::Error(format!("no client with window id {}", window)),
+        },
+        None => wm.active_client(),
+    };
+
+    if let Some(action) = request.action {
+        action.execute(wm, backend, client_option);
+        IpcResponse::Ok(serde_json::Value::Null)
+    } else if let Some(query) = request.query {
+        let result = match query {
+            IpcQuery::AvailableLayouts => serde_json::to_value(available_layouts()),
+            IpcQuery::Clients => serde_json::to_value(clients(wm)),
+            IpcQuery::Layouts => serde_json::to_value(layouts(wm)),
+            IpcQuery::Monitors => serde_json::to_value(monitors(wm)),
+            IpcQuery::Workspaces => serde_json::to_value(workspaces(wm, backend)),
+        };
+        match result {
+            Ok(value) => IpcResponse::Ok(value),
+            Err(e) => IpcResponse::Error(e.to_string()),
+        }
+    } else {
+        IpcResponse::Error("request contains neither action nor query".to_owned())
+    }
+}
+
+pub fn respond(stream: &mut UnixStream, response: IpcResponse) {
+    if let Ok(mut line) = serde_json::to_string(&response) {
+        line.push('\n');
+        let _ = stream.write_all(line.as_bytes());
+    }
+}
+
+fn clients<B: Backend<Attributes>>(wm: &MarsWM<B>) -> Vec<ClientInfo> {
+    let active = wm.active_client();
+    wm.clients().map(|client_rc| {
+        let client = client_rc.borrow();
+        let dimensions = client.dimensions();
+        let pinned = client.attributes().is_pinned;
+        ClientInfo {
+            window: client.id(),
+            title: client.title(),
+            class: client.application(),
+            workspace: wm.get_workspace(client_rc).filter(|_| !pinned).map(|ws| ws.global_index()),
+            monitor: wm.get_monitor(client_rc).and_then(|m| wm.monitors().iter().position(|o| o == m)).map(|i| i as u32),
+            x: dimensions.x(),
+            y: dimensions.y(),
+            width: dimensions.w(),
+            height: dimensions.h(),
+            active: active.as_ref() == Some(client_rc),
+            fullscreen: client.is_fullscreen(),
+            pinned,
+            tiled: wm.is_tiled(client_rc),
+        }
+    }).collect()
+}
+
+fn available_layouts() -> Vec<String> {
+    LayoutType::VALUES.iter().map(|layout| layout.to_string()).collect()
+}
+
+fn layouts<B: Backend<Attributes>>(wm: &MarsWM<B>) -> Vec<LayoutInfo> {
+    wm.monitors().iter().flat_map(|m| m.workspaces()).map(|ws| LayoutInfo {
+        workspace: ws.global_index(),
+        layout: ws.current_layout().to_string(),
+    }).collect()
+}
+
+fn monitors<B: Backend<Attributes>>(wm: &MarsWM<B>) -> Vec<MonitorInfo> {
+    wm.monitors().iter().enumerate().map(|(i, monitor)| {
+        let dimensions = monitor.dimensions();
+        MonitorInfo {
+            index: i as u32,
+            name: monitor.config().name().to_owned(),
+            x: dimensions.x(),
+            y: dimensions.y(),
+            width: dimensions.w(),
+            height: dimensions.h(),
+            workspace: Some(monitor.current_workspace().global_index()),
+        }
+    }).collect()
+}
+
+fn workspaces<B: Backend<Attributes>>(wm: &MarsWM<B>, backend: &mut B) -> Vec<WorkspaceInfo> {
+    let focused = wm.active_workspace(backend);
+    wm.monitors().iter().enumerate().flat_map(|(i, monitor)| {
+        monitor.workspaces().map(move |ws| WorkspaceInfo {
+            index: ws.global_index(),
+            name: ws.name().to_owned(),
+            monitor: i as u32,
+            visible: ws == monitor.current_workspace(),
+            focused: ws.global_index() == focused,
+            clients: ws.clients().count() as u32,
+        })
+    }).collect()
+}
@@ -3,6 +3,7 @@ use libmars::enum_with_values;
 use libmars::wm::Client;
 use serde::{Serialize, Deserialize};
 use std::cell::RefCell;
+use std::fmt;
 use std::rc::Rc;
 
 use crate::attributes::*;
@@ -46,6 +47,17 @@ pub struct Layout<C: Client<Attributes>> {
     apply: fn(Dimensions, &Vec<Rc<RefCell<C>>>, &LayoutConfiguration),
 }
 
+
+impl fmt::Display for LayoutType {
+    /// Name of the layout as used in the configuration (taken from its serde representation)
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match serde_json::to_value(self) {
+            Ok(serde_json::Value::String(name)) => write!(f, "{}", name),
+            _ => write!(f, "{:?}", self),
+        }
+    }
+}
+
 impl<C: Client<Attributes>> Layout<C> {
     pub fn get(layout_type: LayoutType) -> Layout<C> {
         match layout_type {
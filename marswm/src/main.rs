@@ -15,7 +15,9 @@ use crate::marswm::*;
 
 mod attributes;
 mod bindings;
+mod check;
 mod config;
+mod ipc;
 mod layouts;
 mod macros;
 mod marswm;
@@ -31,6 +33,10 @@ const DOCS_URL: &str = "https://jzbor.de/marswm";
 #[derive(Parser)]
 #[clap(author, version, about, long_about = None)]
 pub struct Args {
+    /// Check configuration files for errors and exit
+    #[clap(long)]
+    check_config: bool,
+
     /// Open documentation in the browser
     #[clap(long)]
     docs: bool,
@@ -101,7 +107,11 @@ trait ClientList<C: Client<Attributes>> {
 fn main() {
     let args = Args::parse();
 
-    if args.docs {
+    if args.check_config {
+        if !check::check_config() {
+            std::process::exit(1);
+        }
+    } else if args.docs {
         let result = std::process::Command::new("xdg-open")
             .arg(DOCS_URL)
             .spawn();
@@ -1,31 +1,52 @@
 use libmars::common::*;
-use libmars::common::x11::WINDOW_MIN_SIZE;
-use libmars::wm::{ Backend, Client, WindowManager };
+use libmars::common::x11::{get_keysym, WINDOW_MIN_SIZE};
+use libmars::control::WMEvent;
+use libmars::utils::configuration::read_str;
+use libmars::utils::theme::load_theme;
+use libmars::wm::{ Backend, Client, ClientAction, WindowManager };
 use std::cell::RefCell;
+use std::cmp;
+use std::collections::VecDeque;
 use std::env;
 use std::marker::PhantomData;
 use std::os::unix::process::CommandExt;
 use std::path::PathBuf;
 use std::process;
 use std::rc::Rc;
+use std::time::{Duration, Instant};
 
 use crate::*;
+use crate::ipc::*;
 use crate::monitor::*;
 use crate::rules::*;
 use crate::workspace::*;
 use crate::layouts::LayoutType;
 
+const ACTIVATION_RATE_LIMIT: Duration = Duration::from_secs(1);
+
+/// State of the window switcher while its modifiers are held down
+struct WindowSwitcher<C> {
+    clients: Vec<Rc<RefCell<C>>>,
+    index: usize,
+    grabbed: bool,
+}
 
 pub struct MarsWM<B: Backend<Attributes>> {
     backend_phantom: PhantomData<B>,
     exec_path: PathBuf,
     config: Configuration,
     active_client: Option<Rc<RefCell<B::Client>>>,
+    focus_history: VecDeque<Rc<RefCell<B::Client>>>,  // most recently focused first
     monitors: Vec<Monitor<B::Client>>,
     clients: Vec<Rc<RefCell<B::Client>>>,
     key_bindings: Vec<KeyBinding>,
     button_bindings: Vec<ButtonBinding>,
     rules: Vec<Rule>,
+    last_activation: Option<Instant>,
+    binding_mode: Option<String>,
+    binding_mode_oneshot: bool,
+    window_switcher: Option<WindowSwitcher<B::Client>>,
+    ipc: Option<IpcServer>,
 }
 
 impl<B: Backend<Attributes>> MarsWM<B> {
@@ -38,16 +59,27 @@ impl<B: Backend<Attributes>> MarsWM<B> {
             exec_path: env::current_exe().unwrap(),
             config,
             active_client: None,
+            focus_history: VecDeque::new(),
             clients: Vec::new(),
             monitors: Vec::new(),
             key_bindings,
             button_bindings,
-            rules
+            rules,
+            last_activation: None,
+            binding_mode: None,
+            binding_mode_oneshot: false,
+            window_switcher: None,
+            ipc: IpcServer::start(),
         };
 
+        wm.load_colors(backend);
         let monitor_config = backend.get_monitor_config();
         (&mut wm as &mut dyn WindowManager<B, Attributes>).update_monitor_config(backend, monitor_config);
         backend.export_current_workspace(0);
+        backend.export_binding_mode("");
+        for key_binding in wm.key_bindings.iter().filter(|kb| kb.mode().is_none() && kb.is_global()) {
+            backend.bind_key(key_binding.modifiers(), key_binding.identifier());
+        }
 
         backend.handle_existing_windows(&mut wm);
 
@@ -80,6 +112,10 @@ impl<B: Backend<Attributes>> MarsWM<B> {
                 client_rc.borrow_mut().attributes_mut().is_floating = state;
             }
 
+            if let Some(opacity) = rule.opacity() {
+                client_rc.borrow_mut().attributes_mut().opacity = Some(opacity);
+            }
+
             if rule.initial_placement().is_some() {
                 initial_placement = rule.initial_placement();
             }
@@ -104,6 +140,9 @@ impl<B: Backend<Attributes>> MarsWM<B> {
             client_rc.borrow_mut().show();
             self.unmanage(backend, client_rc);
         }
+        if let Some(ipc) = &self.ipc {
+            ipc.shutdown();
+        }
     }
 
     fn clients_stacked_order(&self) -> Box<dyn Iterator<Item = &Rc<RefCell<B::Client>>> + '_> {
@@ -137,6 +176,27 @@ impl<B: Backend<Attributes>> MarsWM<B> {
         return self.monitors.get_mut(index).unwrap();
     }
 
+    /// Notify subscribers about a new layout on the current workspace
+    pub fn layout_changed(&mut self, backend: &mut B) {
+        let workspace = self.current_workspace(backend);
+        let event = WMEvent::LayoutChanged {
+            workspace: workspace.global_index(),
+            layout: workspace.current_layout().to_string(),
+        };
+        self.publish(event);
+    }
+
+    /// Send an event to subscribers of the IPC socket
+    pub fn publish(&mut self, event: WMEvent) {
+        if let Some(ipc) = &mut self.ipc {
+            ipc.publish(&event);
+        }
+    }
+
+    pub fn monitors(&self) -> &[Monitor<B::Client>] {
+        &self.monitors
+    }
+
     pub fn current_workspace(&self, backend: &mut B) -> &Workspace<B::Client> {
         return self.current_monitor(backend).current_workspace();
     }
@@ -161,12 +221,34 @@ impl<B: Backend<Attributes>> MarsWM<B> {
             };
 
             if let Some(client_rc) = client_opt {
-                client_rc.borrow().warp_pointer_to_center();
                 self.current_workspace_mut(backend).raise_client(&client_rc);
+                self.move_focus(backend, client_rc);
             }
         }
     }
 
+    /// Activate the next client of an application (starting after the active client)
+    ///
+    /// Returns `false` if there is no client of the application.
+    pub fn cycle_application(&mut self, backend: &mut B, class: &str) -> bool {
+        let matches: Vec<_> = self.clients.iter()
+            .filter(|c| c.borrow().application().eq_ignore_ascii_case(class))
+            .cloned()
+            .collect();
+        let next = match self.active_client.as_ref().and_then(|a| matches.iter().position(|c| c == a)) {
+            Some(idx) => matches.get((idx + 1) % matches.len()),
+            None => matches.first(),
+        };
+
+        match next {
+            Some(client_rc) => {
+                self.activate_client(backend, client_rc.clone());
+                true
+            },
+            None => false,
+        }
+    }
+
     pub fn cycle_client(&mut self, backend: &mut B, inc: i32) {
         if let Some(active) = &self.active_client {
             if active.borrow().is_fullscreen() {
@@ -180,8 +262,8 @@ impl<B: Backend<Attributes>> MarsWM<B> {
                 let nclients = ws.tiled_clients().count();
                 let new_idx = ((old_idx + nclients) as i32 + inc) as usize % nclients;
                 let client = ws.tiled_clients().nth(new_idx).unwrap().clone();
-                client.borrow().warp_pointer_to_center();
                 ws.raise_client(&client);
+                self.move_focus(backend, client);
             }
         }
     }
@@ -205,27 +287,190 @@ impl<B: Backend<Attributes>> MarsWM<B> {
     pub fn decorate_active(&self, client_rc: Rc<RefCell<B::Client>>) {
         let mut client = (*client_rc).borrow_mut();
         if self.config.theming.invert_border_color {
-            client.set_inner_color(self.config.theming.inactive_color);
-            client.set_outer_color(self.config.theming.inactive_color);
+            client.set_inner_color(self.config.theming.inactive_color.pixel());
+            client.set_outer_color(self.config.theming.inactive_color.pixel());
         } else {
-            client.set_inner_color(self.config.theming.border_color);
-            client.set_outer_color(self.config.theming.border_color);
+            client.set_inner_color(self.config.theming.border_color.pixel());
+            client.set_outer_color(self.config.theming.border_color.pixel());
         }
-        client.set_title_color(self.config.theming.inactive_color);
-        client.set_frame_color(self.config.theming.active_color);
+        client.set_title_color(self.config.theming.active_title_color.as_ref()
+                               .unwrap_or(&self.config.theming.inactive_color).pixel());
+        client.set_frame_color(self.config.theming.active_color.pixel());
+        let opacity = client.attributes().opacity.unwrap_or(self.config.theming.active_opacity);
+        client.set_opacity(opacity);
     }
 
     pub fn decorate_inactive(&self, client_rc: Rc<RefCell<B::Client>>) {
         let mut client = (*client_rc).borrow_mut();
         if self.config.theming.invert_border_color {
-            client.set_inner_color(self.config.theming.active_color);
-            client.set_outer_color(self.config.theming.active_color);
+            client.set_inner_color(self.config.theming.active_color.pixel());
+            client.set_outer_color(self.config.theming.active_color.pixel());
+        } else {
+            client.set_inner_color(self.config.theming.border_color.pixel());
+            client.set_outer_color(self.config.theming.border_color.pixel());
+        }
+        let inactive_title_color = self.config.theming.inactive_title_color.as_ref()
+            .unwrap_or(&self.config.theming.active_color);
+        if client.is_urgent() {
+            client.set_title_color(self.config.theming.urgent_title_color.as_ref().unwrap_or(inactive_title_color).pixel());
         } else {
-            client.set_inner_color(self.config.theming.border_color);
-            client.set_outer_color(self.config.theming.border_color);
+            client.set_title_color(inactive_title_color.pixel());
+        }
+        client.set_frame_color(self.config.theming.inactive_color.pixel());
+        let opacity = client.attributes().opacity.unwrap_or(self.config.theming.inactive_opacity);
+        client.set_opacity(opacity);
+    }
+
+    fn focus_new_client(&self, client_rc: &Rc<RefCell<B::Client>>) -> bool {
+        let same_application = self.active_client.as_ref()
+            .map(|a| a.borrow().application() == client_rc.borrow().application())
+            .unwrap_or(false);
+
+        match self.config.focus_new_windows {
+            FocusNewWindows::Always => true,
+            FocusNewWindows::Never => false,
+            FocusNewWindows::SameApplication => same_application,
+            FocusNewWindows::Smart => self.active_client.is_none() || same_application
+                || client_rc.borrow().is_dialog(),
+        }
+    }
+
+    /// Move the focus to a client by warping the pointer or focusing it directly
+    fn move_focus(&mut self, backend: &mut B, client_rc: Rc<RefCell<B::Client>>) {
+        let warped = self.warp_pointer_to_client(backend, &client_rc);
+        let focus = match self.config.focus_model {
+            FocusModel::Sloppy => !warped,
+            // strict-click only keeps the pointer from changing the focus
+            FocusModel::Click | FocusModel::StrictClick => true,
+        };
+
+        if focus {
+            self.focus_client(backend, Some(client_rc));
+        }
+    }
+
+    /// Enter a binding mode or leave it with `None`
+    pub fn set_binding_mode(&mut self, backend: &mut B, mode: Option<String>, oneshot: bool) {
+        if mode.is_some() && self.binding_mode.is_none() {
+            // binding modes only work with all keys passed to the window manager
+            if !backend.grab_keyboard() {
+                return;
+            }
+        } else if mode.is_none() && self.binding_mode.is_some() {
+            backend.ungrab_keyboard();
+        }
+
+        backend.export_binding_mode(mode.as_deref().unwrap_or(""));
+        self.binding_mode = mode;
+        self.binding_mode_oneshot = oneshot;
+    }
+
+    /// Cycle through the clients of the current workspace in the order they were last focused
+    ///
+    /// The order is kept until all modifiers are released, so repeated key presses reach older clients.
+    pub fn switch_window(&mut self, backend: &mut B, inc: i32) {
+        let mut started = false;
+        if self.window_switcher.is_none() {
+            // clients that have never been focused follow in stacking order
+            let workspace = self.current_workspace(backend);
+            let mut clients: Vec<_> = self.focus_history.iter().filter(|c| workspace.contains(c)).cloned().collect();
+            let unfocused: Vec<_> = workspace.stacked_clients().filter(|c| !clients.contains(c)).cloned().collect();
+            clients.extend(unfocused);
+
+            // the keyboard must be grabbed to notice the release of the modifiers
+            let grabbed = self.binding_mode.is_none() && backend.grab_keyboard();
+            self.window_switcher = Some(WindowSwitcher { clients, index: 0, grabbed });
+            started = grabbed;
+        }
+
+        let switcher = if let Some(switcher) = self.window_switcher.as_mut() { switcher } else { return };
+        let managed = &self.clients;
+        switcher.clients.retain(|c| managed.contains(c));
+        if switcher.clients.is_empty() {
+            return;
+        }
+        let nclients = switcher.clients.len() as i32;
+        switcher.index = (switcher.index as i32 + inc).rem_euclid(nclients) as usize;
+        let client_rc = switcher.clients[switcher.index].clone();
+        if !switcher.grabbed && self.binding_mode.is_none() {
+            self.window_switcher = None;
+        }
+
+        self.current_workspace_mut(backend).raise_client(&client_rc);
+        self.focus_client(backend, Some(client_rc));
+
+        // the modifiers may have been released before the keyboard was grabbed
+        if started && backend.modifier_state() == 0 {
+            self.end_window_switcher(backend);
+        }
+    }
+
+    fn end_window_switcher(&mut self, backend: &mut B) {
+        if let Some(switcher) = self.window_switcher.take() {
+            if switcher.grabbed {
+                backend.ungrab_keyboard();
+            }
+            if let Some(client_rc) = self.active_client.clone() {
+                self.remember_focus(&client_rc);
+            }
+        }
+    }
+
+    pub fn inc_opacity(&self, client_rc: Rc<RefCell<B::Client>>, inc: f32) {
+        let opacity = match client_rc.borrow().attributes().opacity {
+            Some(opacity) => opacity,
+            None if self.active_client.as_ref() == Some(&client_rc) => self.config.theming.active_opacity,
+            None => self.config.theming.inactive_opacity,
+        };
+        self.set_opacity(client_rc, opacity + inc);
+    }
+
+    pub fn set_opacity(&self, client_rc: Rc<RefCell<B::Client>>, opacity: f32) {
+        client_rc.borrow_mut().attributes_mut().opacity = Some(opacity.clamp(0.0, 1.0));
+        if self.active_client.as_ref() == Some(&client_rc) {
+            self.decorate_active(client_rc);
+        } else {
+            self.decorate_inactive(client_rc);
+        }
+    }
+
+    fn set_focus_on_click(&self, client_rc: &Rc<RefCell<B::Client>>, state: bool) {
+        if self.config.focus_model != FocusModel::Sloppy {
+            client_rc.borrow_mut().set_focus_on_click(state);
+        }
+    }
+
+    fn pointer_warp_permitted(&self, monitor_changed: bool) -> bool {
+        match self.config.pointer_warp {
+            PointerWarp::Never => false,
+            PointerWarp::OnMonitorChange => monitor_changed,
+            PointerWarp::Always => true,
+        }
+    }
+
+    /// Warp the pointer to the center of a client if the pointer_warp setting permits it
+    pub fn warp_pointer_to_client(&self, backend: &B, client_rc: &Rc<RefCell<B::Client>>) -> bool {
+        let monitor_changed = self.get_monitor(client_rc) != Some(self.current_monitor(backend));
+        let permitted = self.pointer_warp_permitted(monitor_changed);
+        if permitted {
+            client_rc.borrow().warp_pointer_to_center();
+        }
+        permitted
+    }
+
+    pub fn export_allowed_actions(&self, client_rc: &Rc<RefCell<B::Client>>) {
+        let mut actions = vec![ClientAction::ChangeWorkspace, ClientAction::Close, ClientAction::Fullscreen];
+        if is_floating!(self, client_rc) {
+            actions.push(ClientAction::Move);
+            actions.push(ClientAction::Resize);
+        }
+        client_rc.borrow().export_allowed_actions(&actions);
+    }
+
+    pub fn refresh_allowed_actions(&self) {
+        for client_rc in &self.clients {
+            self.export_allowed_actions(client_rc);
         }
-        client.set_title_color(self.config.theming.active_color);
-        client.set_frame_color(self.config.theming.inactive_color);
     }
 
     pub fn is_tiled(&self, client_rc: &Rc<RefCell<B::Client>>) -> bool {
@@ -334,6 +579,71 @@ impl<B: Backend<Attributes>> MarsWM<B> {
         self.current_workspace_mut(backend).restack();
     }
 
+    pub fn move_client(&self, client_rc: Rc<RefCell<B::Client>>, dx: i32, dy: i32) {
+        let mut dimensions = client_rc.borrow().dimensions();
+        dimensions.set_x(dimensions.x() + dx);
+        dimensions.set_y(dimensions.y() + dy);
+        self.place_floating_client(client_rc, dimensions);
+    }
+
+    pub fn move_client_to_corner(&self, client_rc: Rc<RefCell<B::Client>>, corner: Corner) {
+        let area = match self.get_monitor(&client_rc) {
+            Some(monitor) => monitor.window_area(),
+            None => return,
+        };
+        let mut dimensions = client_rc.borrow().dimensions();
+        let (x, y) = match corner {
+            Corner::TopLeft => (area.x(), area.y()),
+            Corner::TopRight => (area.right() - dimensions.w() as i32, area.y()),
+            Corner::BottomLeft => (area.x(), area.bottom() - dimensions.h() as i32),
+            Corner::BottomRight => (area.right() - dimensions.w() as i32, area.bottom() - dimensions.h() as i32),
+        };
+        dimensions.set_pos((x, y));
+        self.place_floating_client(client_rc, dimensions);
+    }
+
+    pub fn resize_client(&self, client_rc: Rc<RefCell<B::Client>>, dw: i32, dh: i32) {
+        let mut dimensions = client_rc.borrow().dimensions();
+        dimensions.set_w(cmp::max(dimensions.w() as i32 + dw, WINDOW_MIN_SIZE as i32) as u32);
+        dimensions.set_h(cmp::max(dimensions.h() as i32 + dh, WINDOW_MIN_SIZE as i32) as u32);
+        self.place_floating_client(client_rc, dimensions);
+    }
+
+    pub fn snap_client(&self, client_rc: Rc<RefCell<B::Client>>, edge: Edge) {
+        let area = match self.get_monitor(&client_rc) {
+            Some(monitor) => monitor.window_area(),
+            None => return,
+        };
+        let mut dimensions = client_rc.borrow().dimensions();
+        match edge {
+            Edge::Top => dimensions.set_y(area.y()),
+            Edge::Bottom => dimensions.set_y(area.bottom() - dimensions.h() as i32),
+            Edge::Left => dimensions.set_x(area.x()),
+            Edge::Right => dimensions.set_x(area.right() - dimensions.w() as i32),
+        }
+        self.place_floating_client(client_rc, dimensions);
+    }
+
+    /// Apply new dimensions to a floating client, keeping it inside the window area of its monitor
+    fn place_floating_client(&self, client_rc: Rc<RefCell<B::Client>>, mut dimensions: Dimensions) {
+        if !is_floating!(self, &client_rc) || client_rc.borrow().is_fullscreen() {
+            return;
+        }
+
+        let area = match self.get_monitor(&client_rc) {
+            Some(monitor) => monitor.window_area(),
+            None => return,
+        };
+
+        // shrink client to window area first, then move it inside
+        dimensions.set_w(cmp::max(cmp::min(dimensions.w(), area.w()), WINDOW_MIN_SIZE));
+        dimensions.set_h(cmp::max(cmp::min(dimensions.h(), area.h()), WINDOW_MIN_SIZE));
+        dimensions.set_x(cmp::max(cmp::min(dimensions.x(), area.right() - dimensions.w() as i32), area.x()));
+        dimensions.set_y(cmp::max(cmp::min(dimensions.y(), area.bottom() - dimensions.h() as i32), area.y()));
+
+        client_rc.borrow_mut().set_dimensions(dimensions);
+    }
+
     pub fn move_client_to_monitor(&mut self, client_rc: Rc<RefCell<B::Client>>, inc: i32) {
         let client_index_opt = self.get_monitor(&client_rc).and_then(|cm| self.monitors.iter().position(|m| m == cm));
         let client_index = if let Some(ci) = client_index_opt { ci } else { return };
@@ -402,6 +712,59 @@ impl<B: Backend<Attributes>> MarsWM<B> {
         process::exit(1);
     }
 
+    /// Activate the previously focused client, even if it is on another workspace or monitor
+    pub fn focus_previous(&mut self, backend: &mut B) {
+        let previous = self.focus_history.iter()
+            .find(|c| Some(*c) != self.active_client.as_ref())
+            .cloned();
+        if let Some(client_rc) = previous {
+            self.activate_client(backend, client_rc);
+        }
+    }
+
+    /// Focus another client after the active client has left the current workspace of a monitor
+    ///
+    /// Candidates on the current workspace of the monitor (or the current monitor) are the previously
+    /// focused client, the client under the pointer and the first tiled client, in this order.
+    /// Read the theme and look up the named colors of the configuration
+    fn load_colors(&mut self, backend: &B) {
+        let color_by_name = |name: &str| backend.color_by_name(name);
+        if let Err(msg) = load_theme(&color_by_name) {
+            eprintln!("Unable to read theme: {}", msg);
+        }
+        for msg in self.config.theming.resolve_colors(&color_by_name) {
+            eprintln!("Unable to resolve color: {}", msg);
+        }
+    }
+
+    fn focus_fallback(&mut self, backend: &mut B, monitor_idx: Option<usize>) {
+        if let Some(old_client_rc) = self.active_client.take() {
+            self.set_focus_on_click(&old_client_rc, true);
+        }
+
+        let monitor_idx = monitor_idx.unwrap_or_else(|| self.current_monitor_index(backend));
+        let pointer = backend.pointer_pos();
+        let next = self.monitors.get(monitor_idx).map(|m| m.current_workspace()).and_then(|ws| {
+            self.focus_history.iter().find(|c| ws.contains(c) && c.borrow().is_visible())
+                .or_else(|| ws.stacked_clients().find(|c| c.borrow().is_visible() && c.borrow().contains_point(pointer)))
+                .or_else(|| ws.tiled_clients().next())
+                .cloned()
+        });
+
+        if next.is_some() {
+            self.focus_client(backend, next);
+        } else {
+            backend.export_active_window(&self.active_client);
+            self.publish(WMEvent::FocusChanged { window: None });
+        }
+    }
+
+    /// Move client to the front of the focus history
+    fn remember_focus(&mut self, client_rc: &Rc<RefCell<B::Client>>) {
+        self.focus_history.retain(|c| c != client_rc);
+        self.focus_history.push_front(client_rc.clone());
+    }
+
     pub fn switch_prev_workspace(&mut self, backend: &mut B) {
         let prev_index = self.current_monitor(backend).prev_workspace().global_index();
         self.switch_workspace(backend, prev_index);
@@ -418,6 +781,7 @@ impl<B: Backend<Attributes>> WindowManager<B, Attributes> for MarsWM<B> {
     }
 
     fn activate_client(&mut self, backend: &mut B, client_rc: Rc<RefCell<B::Client>>) {
+        let monitor_changed = self.get_monitor(&client_rc) != Some(self.current_monitor(backend));
         let monitor = self.get_monitor_mut(&client_rc).unwrap();
 
         // switch workspace
@@ -433,7 +797,27 @@ impl<B: Backend<Attributes>> WindowManager<B, Attributes> for MarsWM<B> {
         }
 
         self.focus_client(backend, Some(client_rc.clone()));
-        client_rc.borrow().warp_pointer_to_center();
+        if self.pointer_warp_permitted(monitor_changed) {
+            client_rc.borrow().warp_pointer_to_center();
+        }
+    }
+
+    fn activation_request(&mut self, backend: &mut B, client_rc: Rc<RefCell<B::Client>>) {
+        let allow = match self.config.activation_requests {
+            ActivationPolicy::Allow => true,
+            ActivationPolicy::RateLimit => self.last_activation
+                .map(|t| t.elapsed() >= ACTIVATION_RATE_LIMIT)
+                .unwrap_or(true),
+            ActivationPolicy::Urgency => false,
+        };
+
+        if allow {
+            self.last_activation = Some(Instant::now());
+            self.activate_client(backend, client_rc);
+        } else if self.active_client.as_ref() != Some(&client_rc) {
+            client_rc.borrow_mut().set_urgent(true);
+            self.decorate_inactive(client_rc);
+        }
     }
 
     fn center_client(&mut self, _backend: &mut B, client_rc: Rc<RefCell<B::Client>>) {
@@ -460,25 +844,56 @@ impl<B: Backend<Attributes>> WindowManager<B, Attributes> for MarsWM<B> {
         }
     }
 
+    fn default_frame_extents(&self) -> (u32, u32, u32, u32) {
+        let theming = &self.config.theming;
+        let bw = theming.inner_border_width + theming.outer_border_width;
+        let (north, east, south, west) = theming.frame_width;
+        (north + bw, east + bw, south + bw, west + bw)
+    }
+
+    fn execute_action(&mut self, backend: &mut B, action: &str, client_option: Option<Rc<RefCell<B::Client>>>) {
+        match read_str::<BindingAction>(action) {
+            Ok(action) => action.execute(self, backend, client_option.or(self.active_client.clone())),
+            Err(msg) => eprintln!("Invalid action '{}': {}", action, msg),
+        }
+    }
+
     fn focus_client(&mut self, backend: &mut B, client_option: Option<Rc<RefCell<B::Client>>>) {
         if client_option == self.active_client {
             return;
         } else if let Some(client_rc) = client_option {
             self.decorate_active(client_rc.clone());
             backend.set_input_focus(client_rc.clone());
+            if client_rc.borrow().is_urgent() {
+                client_rc.borrow_mut().set_urgent(false);
+            }
 
             if let Some(old_client_rc) = self.active_client.take() {
+                self.set_focus_on_click(&old_client_rc, true);
                 self.decorate_inactive(old_client_rc);
             }
 
+            self.set_focus_on_click(&client_rc, false);
+            // clients passed by the window switcher are only remembered once it is done
+            if self.window_switcher.is_none() {
+                self.remember_focus(&client_rc);
+            }
             self.active_client = Some(client_rc);
         } else {
-            self.active_client = None;
+            if let Some(old_client_rc) = self.active_client.take() {
+                self.set_focus_on_click(&old_client_rc, true);
+            }
         }
 
         let ws = self.active_workspace(backend);
         backend.export_current_workspace(ws);
         backend.export_active_window(&self.active_client);
+        let window = self.active_client.as_ref().map(|c| c.borrow().id());
+        self.publish(WMEvent::FocusChanged { window });
+    }
+
+    fn focus_follows_pointer(&self) -> bool {
+        self.config.focus_model == FocusModel::Sloppy
     }
 
     fn fullscreen_client(&mut self, _backend: &mut B, client_rc: Rc<RefCell<B::Client>>, state: bool) {
@@ -498,6 +913,10 @@ impl<B: Backend<Attributes>> WindowManager<B, Attributes> for MarsWM<B> {
     fn handle_button(&mut self, backend: &mut B, modifiers: u32, button: u32, target: ButtonTarget,
                      client_option: Option<Rc<RefCell<B::Client>>>) {
         if let Some(client) = client_option.clone() {
+            if target != ButtonTarget::Root && !self.focus_follows_pointer() {
+                self.focus_client(backend, Some(client.clone()));
+            }
+
             if let Some(ws) = self.get_workspace_mut(&client) {
                 ws.raise_client(&client);
             } else {
@@ -506,21 +925,72 @@ impl<B: Backend<Attributes>> WindowManager<B, Attributes> for MarsWM<B> {
             };
         }
 
-        let actions: Vec<BindingAction> = self.button_bindings.iter().filter(|bb| bb.matches(modifiers, button, target))
-            .map(|kb| kb.action()).collect();
+        let mut actions: Vec<BindingAction> = self.button_bindings.iter().filter(|bb| bb.matches(modifiers, button, target))
+            .flat_map(|bb| bb.actions().iter().cloned()).collect();
+
+        // title buttons execute their configured actions on left click
+        if let ButtonTarget::TitleButton(index) = target {
+            if button == 1 {
+                if let Some(title_button) = self.config.theming.title_buttons.get(index as usize) {
+                    actions.extend(title_button.actions.iter().cloned());
+                }
+            }
+        }
+
+        // the title is part of the frame, so frame bindings apply if there is no specific binding
+        if actions.is_empty() && target == ButtonTarget::Title {
+            actions = self.button_bindings.iter().filter(|bb| bb.matches(modifiers, button, ButtonTarget::Frame))
+                .flat_map(|bb| bb.actions().iter().cloned()).collect();
+        }
         for action in actions {
             action.execute(self, backend, client_option.clone());
         }
     }
 
-    fn handle_key(&mut self, backend: &mut B, modifiers: u32, key: u32, client_option: Option<Rc<RefCell<B::Client>>>) {
-        let actions: Vec<BindingAction> = self.key_bindings.iter().filter(|kb| kb.matches(modifiers, key))
-            .map(|kb| kb.action()).collect();
+    fn handle_requests(&mut self, backend: &mut B) {
+        let requests = match &self.ipc {
+            Some(ipc) => ipc.pending(),
+            None => return,
+        };
+
+        for (request, mut stream) in requests {
+            if request.subscribe {
+                if let Some(ipc) = &mut self.ipc {
+                    ipc.subscribe(stream);
+                }
+            } else {
+                let response = handle_request(self, backend, request);
+                respond(&mut stream, response);
+            }
+        }
+    }
+
+    fn handle_key(&mut self, backend: &mut B, modifiers: u32, keycode: u32, keysym: u32, release: bool,
+                  client_option: Option<Rc<RefCell<B::Client>>>) {
+        let mode = self.binding_mode.clone();
+        let actions: Vec<BindingAction> = self.key_bindings.iter()
+            .filter(|kb| kb.mode() == mode.as_deref() && kb.matches(modifiers, keycode, keysym, release))
+            .flat_map(|kb| kb.actions().iter().cloned()).collect();
+
+        // the keyboard is grabbed on the root window in binding modes and for global keys
+        let client_option = client_option.or(self.active_client.clone());
+
+        if mode.is_some() && !release
+                && (self.binding_mode_oneshot || (actions.is_empty() && keysym == get_keysym("Escape") as u32)) {
+            self.set_binding_mode(backend, None, false);
+        }
+
         for action in actions {
             action.execute(self, backend, client_option.clone());
         }
     }
 
+    fn handle_modifier_release(&mut self, backend: &mut B, modifiers: u32) {
+        if modifiers == 0 {
+            self.end_window_switcher(backend);
+        }
+    }
+
     fn manage(&mut self, backend: &mut B, client_rc: Rc<RefCell<B::Client>>, workspace_preference: Option<u32>) {
         let (workspace_idx, actions, initial_placement)
                 = match self.apply_window_rules(backend, client_rc.clone(), workspace_preference) {
@@ -553,7 +1023,16 @@ impl<B: Backend<Attributes>> WindowManager<B, Attributes> for MarsWM<B> {
                 let _ignore_result = client.add_title(&self.config.theming.font,
                                                       self.config.theming.title_hpadding,
                                                       self.config.theming.title_vpadding,
-                                                      self.config.theming.active_color);
+                                                      self.config.theming.active_color.pixel(),
+                                                      self.config.theming.title_alignment,
+                                                      &self.config.theming.title_format);
+            }
+            if !self.config.theming.title_buttons.is_empty() {
+                let glyphs: Vec<String> = self.config.theming.title_buttons.iter().map(|b| b.glyph.clone()).collect();
+                let _ignore_result = client.add_title_buttons(&self.config.theming.font,
+                                                              self.config.theming.title_hpadding,
+                                                              self.config.theming.title_vpadding,
+                                                              self.config.theming.active_color.pixel(), &glyphs);
             }
         } else {
             client.set_inner_bw(self.config.theming.no_decoration.inner_border_width);
@@ -563,8 +1042,8 @@ impl<B: Backend<Attributes>> WindowManager<B, Attributes> for MarsWM<B> {
 
 
         // bind keys and buttons
-        for key_binding in &self.key_bindings {
-            client.bind_key(key_binding.modifiers(), key_binding.key());
+        for key_binding in self.key_bindings.iter().filter(|kb| kb.mode().is_none() && !kb.is_global()) {
+            client.bind_key(key_binding.modifiers(), key_binding.identifier());
         }
         for button_binding in &self.button_bindings {
             for target in button_binding.targets() {
@@ -575,6 +1054,8 @@ impl<B: Backend<Attributes>> WindowManager<B, Attributes> for MarsWM<B> {
         }
 
         drop(client);
+        self.decorate_inactive(client_rc.clone());
+        self.set_focus_on_click(&client_rc, true);
 
         if Some(self.current_workspace(backend)) == self.get_workspace(&client_rc) {
             client_rc.borrow_mut().show();
@@ -596,16 +1077,18 @@ impl<B: Backend<Attributes>> WindowManager<B, Attributes> for MarsWM<B> {
             workspace.drop_fullscreen();
             workspace.restack();
         }
+        self.export_allowed_actions(&client_rc);
 
         // set client as currently focused
-        if client_rc.borrow().is_visible() {
+        if client_rc.borrow().is_visible() && self.focus_new_client(&client_rc) {
             self.focus_client(backend, Some(client_rc.clone()));
-            client_rc.borrow_mut().warp_pointer_to_center();
+            self.warp_pointer_to_client(backend, &client_rc);
         }
 
         let clients = <marswm::MarsWM<B> as WindowManager<B, Attributes>>::clients(self).collect();
         let clients_stacked = self.clients_stacked_order().collect();
         backend.export_client_list(clients, clients_stacked);
+        self.publish(WMEvent::ClientManaged { window: client_rc.borrow().id() });
 
         // apply window rule actions
         actions.iter().for_each(|a| a.execute(self, backend, Some(client_rc.clone())))
@@ -630,6 +1113,7 @@ impl<B: Backend<Attributes>> WindowManager<B, Attributes> for MarsWM<B> {
             return;
         }
 
+        let from_monitor = self.monitors.iter().position(|m| m.workspaces().any(|ws| ws.contains(&client_rc)));
         let from_workspace = match self.get_workspace_mut(&client_rc) {
             Some(workspace) => workspace,
             None => return,
@@ -641,6 +1125,8 @@ impl<B: Backend<Attributes>> WindowManager<B, Attributes> for MarsWM<B> {
             None => panic!("Window is detached but cannot be attached again"),
         };
         to_workspace.attach_client(client_rc.clone());
+        // the new workspace might use a different layout
+        self.export_allowed_actions(&client_rc);
 
         // adjust position to be on workspace
         Self::fix_client_to_area(client_rc.clone(), self.get_monitor_mut(&client_rc).unwrap().window_area());
@@ -650,10 +1136,9 @@ impl<B: Backend<Attributes>> WindowManager<B, Attributes> for MarsWM<B> {
             client_rc.borrow_mut().hide();
         }
 
-        // TODO focus other client or drop focus
-        // hacky workaround:
-        self.active_client = None;
-        backend.export_active_window(&self.active_client);
+        if self.active_client.as_ref() == Some(&client_rc) {
+            self.focus_fallback(backend, from_monitor);
+        }
     }
 
     fn resize_request(&mut self, _backend: &mut B, client_rc: Rc<RefCell<B::Client>>, width: u32, height: u32) -> bool {
@@ -680,6 +1165,18 @@ impl<B: Backend<Attributes>> WindowManager<B, Attributes> for MarsWM<B> {
         }
     }
 
+    fn resources_changed(&mut self, backend: &mut B) {
+        self.load_colors(backend);
+
+        for client_rc in self.clients.clone() {
+            if self.active_client.as_ref() == Some(&client_rc) {
+                self.decorate_active(client_rc);
+            } else {
+                self.decorate_inactive(client_rc);
+            }
+        }
+    }
+
     fn set_client_pinned(&mut self, _backend: &mut B, client_rc: Rc<RefCell<B::Client>>, state: bool) {
         if let Some(ws) = self.get_workspace_mut(&client_rc) {
             ws.set_pinned(client_rc, state);
@@ -689,9 +1186,10 @@ impl<B: Backend<Attributes>> WindowManager<B, Attributes> for MarsWM<B> {
     fn tile_client(&mut self, _backend: &mut B, client_rc: Rc<RefCell<B::Client>>, state: bool) {
         if let Some(ws) = self.get_workspace_mut(&client_rc) {
             if ws.current_layout() != LayoutType::Floating {
-                ws.set_floating(client_rc, !state);
+                ws.set_floating(client_rc.clone(), !state);
             }
         }
+        self.export_allowed_actions(&client_rc);
     }
 
     fn switch_workspace(&mut self, backend: &mut B, workspace_idx: u32) {
@@ -703,6 +1201,7 @@ impl<B: Backend<Attributes>> WindowManager<B, Attributes> for MarsWM<B> {
         let (mon_idx, rel_idx) = self.relative_workspace_idx(workspace_idx);
 
         // switch monitor if necessary
+        // (regardless of the pointer_warp setting, as the current monitor is determined by the pointer)
         if mon_idx >= self.monitors.len() {
             return;
         } else if mon_idx != self.current_monitor_index(backend) {
@@ -739,6 +1238,12 @@ impl<B: Backend<Attributes>> WindowManager<B, Attributes> for MarsWM<B> {
         self.focus_client(backend, new_active);
 
         backend.export_current_workspace(workspace_idx);
+        self.publish(WMEvent::WorkspaceSwitched { workspace: workspace_idx });
+    }
+
+    fn title_changed(&mut self, _backend: &mut B, client_rc: Rc<RefCell<B::Client>>) {
+        let (window, title) = (client_rc.borrow().id(), client_rc.borrow().title());
+        self.publish(WMEvent::TitleChanged { window, title });
     }
 
     fn toggle_fullscreen_client(&mut self, backend: &mut B, client_rc: Rc<RefCell<B::Client>>) {
@@ -758,20 +1263,26 @@ impl<B: Backend<Attributes>> WindowManager<B, Attributes> for MarsWM<B> {
             self.clients.remove(index);
         }
 
+        let monitor_idx = self.monitors.iter().position(|m| m.clients().any(|c| c == &client_rc));
+
         // remove from monitor data structure
         for mon in &mut self.monitors {
             mon.detach_client(&client_rc)
         }
+        self.focus_history.retain(|c| c != &client_rc);
+
+        let window = client_rc.borrow().id();
 
-        // unset client as currently active
+        // unset client as currently active and pass focus on
         if Some(client_rc) == self.active_client {
             self.active_client = None;
-            backend.export_active_window(&self.active_client);
+            self.focus_fallback(backend, monitor_idx);
         }
 
         let clients = <marswm::MarsWM<B> as WindowManager<B, Attributes>>::clients(self).collect();
         let clients_stacked = self.clients_stacked_order().collect();
         backend.export_client_list(clients, clients_stacked);
+        self.publish(WMEvent::ClientUnmanaged { window });
     }
 
     fn update_monitor_config(&mut self, backend: &mut B, configs: Vec<MonitorConfig>) {
@@ -812,10 +1323,17 @@ impl<B: Backend<Attributes>> WindowManager<B, Attributes> for MarsWM<B> {
 
         }
 
+        // the active client might have been moved to a hidden workspace of the remaining monitors
+        if configs.len() < cur_monitor_count
+                && !self.active_client.as_ref().is_some_and(|c| c.borrow().is_visible()) {
+            self.focus_fallback(backend, None);
+        }
+
         // export desktop settings
         let workspace_info = self.monitors.iter()
             .flat_map(|m| m.workspaces().map(|ws| (ws.name().to_owned(), m.dimensions(), m.window_area())))
             .collect();
         backend.export_workspaces(workspace_info);
+        self.publish(WMEvent::MonitorsChanged);
     }
 }
@@ -23,6 +23,9 @@ pub struct Rule {
     /// initially make this window floating
     floating: Option<bool>,
 
+    /// opacity (0.0 - 1.0) overriding the theming configuration
+    opacity: Option<f32>,
+
     /// preferred workspace for the application to get launched on
     workspace: Option<u32>,
 }
@@ -54,6 +57,10 @@ impl Rule {
         self.ignore_window
     }
 
+    pub fn opacity(&self) -> Option<f32> {
+        self.opacity
+    }
+
     pub fn initial_placement(&self) -> Option<WindowPlacement> {
         self.initial_placement
     }
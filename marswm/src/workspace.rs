@@ -254,7 +254,6 @@ impl<C: Client<Attributes>> Workspace<C> {
             self.clients.remove(pos);
             self.clients.insert(new_pos, client_rc.clone());
             self.apply_layout();
-            client_rc.borrow().warp_pointer_to_center();
         }
     }
 
@@ -270,6 +269,11 @@ impl<C: Client<Attributes>> Workspace<C> {
         return Box::new(self.clients.iter().filter(|c| !c.borrow().attributes().is_floating));
     }
 
+    /// Clients sorted by stacking order (most recently raised first)
+    pub fn stacked_clients(&self) -> Box<dyn Iterator<Item = &Rc<RefCell<C>>> + '_> {
+        Box::new(self.clients_stack.iter())
+    }
+
     pub fn update_window_area(&mut self, win_area: Dimensions) {
         self.win_area = win_area;
         self.apply_layout();